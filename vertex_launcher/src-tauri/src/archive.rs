@@ -0,0 +1,545 @@
+use crate::download::GameDownload;
+use crate::env::UPDATE_RATE;
+use crate::errors::Verror::GameResourceExtractionError;
+use crate::{errors, paths};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// # Archives module
+/// Extract the game archives published by the catalog. Every format is read by its own backend
+/// (an `ArchiveReader`), which only lists the entries of the archive. Writing the entries to the disk
+/// and stripping the top level folder is shared, so every format behaves the same way.
+
+/// The flag set in the attributes of a 7z entry when its high 16 bits hold its Unix mode.
+const SEVENZ_UNIX_EXTENSION: u32 = 0x8000;
+
+/// The size of the buffer used to write the extracted files.
+const EXTRACTION_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The name the top level folder is renamed to while its content is moved up to the destination.
+const TOP_LEVEL_TEMPORARY_NAME: &str = ".vertex_top_level";
+
+/// The formats a game archive can be published in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+    TarXz,
+    TarZst,
+    #[serde(rename = "7z")]
+    SevenZ,
+}
+
+impl ArchiveFormat {
+    /// Guess the format of an archive from the extension of its file name.
+    pub fn from_file_name(file_name: &str) -> Option<ArchiveFormat> {
+        let file_name = file_name.to_lowercase();
+        if file_name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if file_name.ends_with(".tar.xz") || file_name.ends_with(".txz") {
+            Some(ArchiveFormat::TarXz)
+        } else if file_name.ends_with(".tar.zst") || file_name.ends_with(".tzst") {
+            Some(ArchiveFormat::TarZst)
+        } else if file_name.ends_with(".7z") {
+            Some(ArchiveFormat::SevenZ)
+        } else {
+            None
+        }
+    }
+}
+
+/// An entry of an archive, whatever its format.
+pub struct ArchiveEntry {
+    /// Path of the entry, relative to the root of the archive.
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    /// Unix permissions of the entry, None if the archive doesn't store them.
+    pub mode: Option<u32>,
+}
+
+pub enum EntryKind {
+    File,
+    Directory,
+    /// A symbolic link pointing to the given target.
+    Symlink(PathBuf),
+    /// A hard link to a previous entry, at the given path relative to the root of the archive.
+    HardLink(PathBuf),
+}
+
+/// The extraction backend of a format.
+trait ArchiveReader {
+    /// Return the number of entries and the extracted size (in bytes) of the archive,
+    /// or None if the format can only know them by reading the whole archive.
+    fn get_totals(&mut self) -> errors::Result<Option<(u64, u64)>>;
+
+    /// Call `visit` with each entry of the archive, in the archive order, and a reader over its content.
+    fn for_each_entry(
+        &mut self,
+        visit: &mut dyn FnMut(ArchiveEntry, &mut dyn Read) -> errors::Result<()>,
+    ) -> errors::Result<()>;
+}
+
+struct ZipReader(zip::ZipArchive<BufReader<File>>);
+
+impl ArchiveReader for ZipReader {
+    fn get_totals(&mut self) -> errors::Result<Option<(u64, u64)>> {
+        let mut total_size = 0;
+        for index in 0..self.0.len() {
+            total_size += self.0.by_index_raw(index)?.size();
+        }
+        Ok(Some((self.0.len() as u64, total_size)))
+    }
+
+    fn for_each_entry(
+        &mut self,
+        visit: &mut dyn FnMut(ArchiveEntry, &mut dyn Read) -> errors::Result<()>,
+    ) -> errors::Result<()> {
+        for index in 0..self.0.len() {
+            let mut file = self.0.by_index(index)?;
+            let path = file
+                .enclosed_name()
+                .ok_or(GameResourceExtractionError(format!(
+                    "invalid entry path \"{}\"",
+                    file.name()
+                )))?;
+            let kind = if file.is_dir() {
+                EntryKind::Directory
+            } else if file.is_symlink() {
+                // The content of a symbolic link entry is its target
+                let mut link_target = String::new();
+                file.read_to_string(&mut link_target)?;
+                EntryKind::Symlink(PathBuf::from(link_target))
+            } else {
+                EntryKind::File
+            };
+            let mode = file.unix_mode();
+            visit(ArchiveEntry { path, kind, mode }, &mut file)?;
+        }
+        Ok(())
+    }
+}
+
+struct TarReader<R: Read>(tar::Archive<R>);
+
+impl<R: Read> ArchiveReader for TarReader<R> {
+    fn get_totals(&mut self) -> errors::Result<Option<(u64, u64)>> {
+        // Tar archives are a stream of entries without any index
+        Ok(None)
+    }
+
+    fn for_each_entry(
+        &mut self,
+        visit: &mut dyn FnMut(ArchiveEntry, &mut dyn Read) -> errors::Result<()>,
+    ) -> errors::Result<()> {
+        for entry in self.0.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+            let mode = entry.header().mode().ok();
+            let kind = match entry.header().entry_type() {
+                tar::EntryType::Regular | tar::EntryType::Continuous => EntryKind::File,
+                tar::EntryType::Directory => EntryKind::Directory,
+                tar::EntryType::Symlink => match entry.link_name()? {
+                    Some(target) => EntryKind::Symlink(target.into_owned()),
+                    None => continue,
+                },
+                tar::EntryType::Link => match entry.link_name()? {
+                    Some(target) => EntryKind::HardLink(target.into_owned()),
+                    None => continue,
+                },
+                entry_type => {
+                    info!(
+                        "Skipping unsupported tar entry \"{}\" ({:?})",
+                        path.display(),
+                        entry_type
+                    );
+                    continue;
+                }
+            };
+            visit(ArchiveEntry { path, kind, mode }, &mut entry)?;
+        }
+        Ok(())
+    }
+}
+
+struct SevenZReader(sevenz_rust::SevenZReader<File>);
+
+impl ArchiveReader for SevenZReader {
+    fn get_totals(&mut self) -> errors::Result<Option<(u64, u64)>> {
+        let files = &self.0.archive().files;
+        Ok(Some((
+            files.len() as u64,
+            files.iter().map(|file| file.size()).sum(),
+        )))
+    }
+
+    fn for_each_entry(
+        &mut self,
+        visit: &mut dyn FnMut(ArchiveEntry, &mut dyn Read) -> errors::Result<()>,
+    ) -> errors::Result<()> {
+        // The backend callback can't return our errors, so the first one is kept to stop the iteration
+        let mut visit_error = None;
+        self.0
+            .for_each_entries(|entry, reader| {
+                let kind = if entry.is_directory() {
+                    EntryKind::Directory
+                } else {
+                    EntryKind::File
+                };
+                let attributes = entry.windows_attributes();
+                let archive_entry = ArchiveEntry {
+                    path: PathBuf::from(entry.name()),
+                    kind,
+                    mode: (attributes & SEVENZ_UNIX_EXTENSION != 0).then_some(attributes >> 16),
+                };
+                match visit(archive_entry, reader) {
+                    Ok(_) => Ok(true),
+                    Err(e) => {
+                        visit_error = Some(e);
+                        Ok(false)
+                    }
+                }
+            })
+            .map_err(|e| GameResourceExtractionError(e.to_string()))?;
+
+        match visit_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Open the backend of the archive format.
+fn open_archive(
+    archive_path: &Path,
+    format: ArchiveFormat,
+) -> errors::Result<Box<dyn ArchiveReader>> {
+    let file = File::open(archive_path)?;
+    Ok(match format {
+        ArchiveFormat::Zip => Box::new(ZipReader(zip::ZipArchive::new(BufReader::new(file))?)),
+        ArchiveFormat::TarGz => Box::new(TarReader(tar::Archive::new(
+            flate2::read::GzDecoder::new(BufReader::new(file)),
+        ))),
+        ArchiveFormat::TarXz => Box::new(TarReader(tar::Archive::new(xz2::read::XzDecoder::new(
+            BufReader::new(file),
+        )))),
+        ArchiveFormat::TarZst => Box::new(TarReader(tar::Archive::new(
+            zstd::stream::read::Decoder::new(file)?,
+        ))),
+        ArchiveFormat::SevenZ => Box::new(SevenZReader(
+            sevenz_rust::SevenZReader::new(
+                file,
+                fs::metadata(archive_path)?.len(),
+                sevenz_rust::Password::empty(),
+            )
+            .map_err(|e| GameResourceExtractionError(e.to_string()))?,
+        )),
+    })
+}
+
+/// Extract every entry of the archive in the destination folder, with the permissions it stores.<br>
+/// If `strip_top_level_folder` is true and every entry is inside the same top level folder,
+/// the content of this folder is moved up to the destination once extracted. Otherwise the entries are left as they are.
+/// The destination must not hold other folders, such as a previous extraction.<br>
+/// When `only_files` is provided, only the files at these paths ("/" separated, relative to the destination)
+/// are extracted, with or without the top level folder.<br>
+/// The entries processed and the bytes written are reported through `download`. When the archive
+/// format doesn't list its content upfront, or only some files are extracted, the bytes are reported
+/// against `expected_size`.
+pub fn extract(
+    archive_path: &Path,
+    format: ArchiveFormat,
+    destination: &Path,
+    strip_top_level_folder: bool,
+    only_files: Option<&HashSet<String>>,
+    expected_size: Option<u64>,
+    download: &mut GameDownload,
+) -> errors::Result<()> {
+    info!(
+        "Extracting \"{}\" ({:?}) to \"{}\"",
+        archive_path.display(),
+        format,
+        destination.display()
+    );
+    let mut reader = open_archive(archive_path, format)?;
+
+    let totals = match only_files {
+        Some(only_files) => Some((only_files.len() as u64, expected_size.unwrap_or(0))),
+        None => reader.get_totals()?,
+    };
+    let mut progress = ExtractionProgress::new(
+        download,
+        totals.map(|(total_entries, _)| total_entries),
+        totals.map(|(_, total_size)| total_size).or(expected_size),
+    );
+
+    // Tar archives are a stream: the top level folder is found while extracting, and stripped afterwards
+    let mut top_level_folder = TopLevelFolder::Empty;
+    let result = reader.for_each_entry(&mut |entry, data| {
+        top_level_folder.add(&entry);
+        if only_files.is_some_and(|only_files| {
+            !only_files.contains(&to_entry_key(&entry.path))
+                && !(strip_top_level_folder
+                    && strip_top_level(&entry.path).is_some_and(|relative_path| {
+                        only_files.contains(&to_entry_key(&relative_path))
+                    }))
+        }) {
+            return Ok(());
+        }
+        progress.add_entry();
+        let target = paths::join_inside_without_symlink(destination, &entry.path)?;
+
+        match entry.kind {
+            EntryKind::Directory => fs::create_dir_all(&target)?,
+            EntryKind::File => {
+                create_parent(&target)?;
+                remove_symlink(&target)?;
+                copy_with_progress(data, &mut File::create(&target)?, &mut progress)?;
+                restore_permissions(&target, entry.mode)?;
+            }
+            EntryKind::Symlink(link_target) => {
+                // The link must not give access to files outside the destination either
+                paths::check_link_target(&link_target)?;
+                create_parent(&target)?;
+                remove_symlink(&target)?;
+                create_symlink(&link_target, &target)?;
+            }
+            EntryKind::HardLink(link_target) => {
+                // The linked entry comes first in the archive, its content is copied
+                let source = paths::join_inside_without_symlink(destination, &link_target)?;
+                if source.is_file() {
+                    create_parent(&target)?;
+                    remove_symlink(&target)?;
+                    fs::copy(&source, &target)?;
+                } else {
+                    info!(
+                        "Skipping hard link \"{}\", \"{}\" is not extracted",
+                        target.display(),
+                        link_target.display()
+                    );
+                }
+            }
+        }
+        Ok(())
+    });
+    // advertise a last time to get the 100% of progress
+    progress.advertise();
+    result?;
+
+    if strip_top_level_folder {
+        match top_level_folder {
+            TopLevelFolder::Single(folder) => move_top_level_content(destination, &folder)?,
+            _ => info!("The entries are not inside a single top level folder, it is not stripped"),
+        }
+    }
+    Ok(())
+}
+
+/// Report the progress of an extraction, at most once every `UPDATE_RATE` milliseconds.
+struct ExtractionProgress<'a> {
+    download: &'a mut GameDownload,
+    processed_entries: u64,
+    total_entries: Option<u64>,
+    written: u64,
+    last_update: Instant,
+}
+
+impl<'a> ExtractionProgress<'a> {
+    fn new(
+        download: &'a mut GameDownload,
+        total_entries: Option<u64>,
+        total_size: Option<u64>,
+    ) -> Self {
+        // The speed and remaining time are computed from the start of the extraction
+        download.set_start_time(Instant::now());
+        download.set_file_size(total_size.unwrap_or(0));
+        download.update(0, None);
+        download.update_entries(0, total_entries);
+        Self {
+            download,
+            processed_entries: 0,
+            total_entries,
+            written: 0,
+            last_update: Instant::now() - Duration::from_millis(UPDATE_RATE as u64),
+        }
+    }
+
+    fn add_entry(&mut self) {
+        self.processed_entries += 1;
+        self.download
+            .update_entries(self.processed_entries, self.total_entries);
+        self.advertise_throttled();
+    }
+
+    fn add_bytes(&mut self, written: u64) {
+        self.written += written;
+        // The declared installed size is only an estimation, never report more than 100%
+        if self.written > self.download.file_size {
+            self.download.set_file_size(self.written);
+        }
+        self.download.update(self.written, None);
+        self.advertise_throttled();
+    }
+
+    fn advertise_throttled(&mut self) {
+        if (self.last_update.elapsed().as_millis() as u16) >= UPDATE_RATE {
+            self.advertise();
+        }
+    }
+
+    fn advertise(&mut self) {
+        self.download.advertise();
+        self.last_update = Instant::now();
+    }
+}
+
+/// Write the content of an entry to the file, reporting the bytes written as they go.
+fn copy_with_progress(
+    data: &mut dyn Read,
+    file: &mut File,
+    progress: &mut ExtractionProgress,
+) -> io::Result<()> {
+    let mut buffer = vec![0; EXTRACTION_BUFFER_SIZE];
+    loop {
+        let read = match data.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        file.write_all(&buffer[..read])?;
+        progress.add_bytes(read as u64);
+    }
+}
+
+/// Return the path of an entry in the format used by the install manifests, e.g. "bin/game.pak".
+fn to_entry_key(relative_path: &Path) -> String {
+    relative_path
+        .components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect::<PathBuf>()
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// The top level folder holding the entries seen so far.
+enum TopLevelFolder {
+    Empty,
+    Single(OsString),
+    /// Some entries are outside of the first top level folder.
+    Several,
+}
+
+impl TopLevelFolder {
+    fn add(&mut self, entry: &ArchiveEntry) {
+        if matches!(self, TopLevelFolder::Several) {
+            return;
+        }
+        // Tar archives often prefix their entries with "./"
+        let mut components = entry
+            .path
+            .components()
+            .filter(|component| !matches!(component, Component::CurDir));
+        let Some(first) = components.next() else {
+            return;
+        };
+        // A file at the root of the archive
+        if components.next().is_none() && !matches!(entry.kind, EntryKind::Directory) {
+            *self = TopLevelFolder::Several;
+            return;
+        }
+        match self {
+            TopLevelFolder::Empty => *self = TopLevelFolder::Single(first.as_os_str().to_owned()),
+            TopLevelFolder::Single(folder) if folder.as_os_str() != first.as_os_str() => {
+                *self = TopLevelFolder::Several
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Move the content of the extracted top level folder up to the destination.
+fn move_top_level_content(destination: &Path, folder: &OsStr) -> io::Result<()> {
+    let top_level_folder = destination.join(folder);
+    // Nothing was extracted from it
+    if !top_level_folder.is_dir() {
+        return Ok(());
+    }
+    // The folder is renamed first, it can hold an entry with its own name
+    let temporary_folder = destination.join(TOP_LEVEL_TEMPORARY_NAME);
+    fs::rename(&top_level_folder, &temporary_folder)?;
+    for entry in fs::read_dir(&temporary_folder)? {
+        let entry = entry?;
+        fs::rename(entry.path(), destination.join(entry.file_name()))?;
+    }
+    fs::remove_dir(&temporary_folder)
+}
+
+/// Return the path of an entry without its top level folder, or None if the entry is the top level folder.
+fn strip_top_level(path: &Path) -> Option<PathBuf> {
+    let relative_path: PathBuf = path
+        .components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .skip(1)
+        .collect();
+    (!relative_path.as_os_str().is_empty()).then_some(relative_path)
+}
+
+fn create_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) => fs::create_dir_all(parent),
+        None => Ok(()),
+    }
+}
+
+/// Remove the symbolic link left at `path` by a previous install, so the new entry isn't written through it.
+fn remove_symlink(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+/// Restore the permissions stored in the archive.<br>
+/// The owner always keeps the right to read and write the file, so the next update can replace it.
+#[cfg(unix)]
+fn restore_permissions(path: &Path, mode: Option<u32>) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    match mode {
+        Some(mode) => fs::set_permissions(path, fs::Permissions::from_mode((mode & 0o777) | 0o600)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn restore_permissions(_path: &Path, _mode: Option<u32>) -> io::Result<()> {
+    Ok(())
+}
+
+/// Create a symbolic link at `path` pointing to `link_target`.
+#[cfg(unix)]
+pub fn create_symlink(link_target: &Path, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(link_target, path)
+}
+
+#[cfg(not(unix))]
+pub fn create_symlink(link_target: &Path, path: &Path) -> io::Result<()> {
+    // Symbolic links need extra privileges on Windows, copy the target instead when it's already extracted
+    let source = path.parent().unwrap_or(Path::new("")).join(link_target);
+    if source.is_file() {
+        fs::copy(source, path)?;
+    } else {
+        info!("Skipping symbolic link \"{}\"", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use crate::errors::Verror::{
+    CredentialsExpired, CredentialsMissing, CredentialsRejected, GameResourceDownloadError,
+    StoreAccessError,
+};
+use crate::games::Link;
+use crate::{env, errors, http};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use tauri_plugin_http::reqwest::{RequestBuilder, Response, StatusCode};
+use tauri_plugin_store::StoreExt;
+
+/// # Authentication module
+/// Links of private and early-access builds can require credentials. The credentials are grouped by realm
+/// (e.g. "closed_beta") and saved in the store under `env::STORE_CREDENTIALS_KEY`.<br>
+/// Access tokens close to their expiration are refreshed with their refresh token before being used.
+
+/// How the credentials of a realm are used to fetch a link.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "scheme", rename_all = "snake_case")]
+pub enum LinkAuth {
+    /// The url is fetched with the access token of the realm as a bearer token.
+    Bearer { realm: String },
+    /// A signed url is requested to the studio `endpoint` with the access token of the realm.
+    /// The signed url is then fetched without credentials.
+    SignedUrl { realm: String, endpoint: String },
+}
+
+/// The credentials of a realm.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credentials {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// Unix timestamp (in seconds) at which the access token expires. None if it never expires.
+    pub expires_at: Option<u64>,
+    /// The OAuth2 endpoint used to refresh the access token with the refresh token.
+    pub token_endpoint: Option<String>,
+}
+
+/// Response of a token endpoint to a refresh request.
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+}
+
+/// Response of a studio endpoint to a signed url request.
+#[derive(Debug, Deserialize)]
+struct SignedUrlResponse {
+    url: String,
+}
+
+/// Build the GET request of a link, with its credentials attached.
+pub async fn authorize_request(app: &AppHandle, link: &Link) -> errors::Result<RequestBuilder> {
+    let client = http::get_client(app);
+    match &link.auth {
+        None => Ok(client.get(&link.url)),
+        Some(LinkAuth::Bearer { realm }) => {
+            let access_token = get_access_token(app, realm).await?;
+            Ok(client.get(&link.url).bearer_auth(access_token))
+        }
+        Some(LinkAuth::SignedUrl { realm, endpoint }) => {
+            let access_token = get_access_token(app, realm).await?;
+            let response = client
+                .get(endpoint)
+                .query(&[("url", &link.url)])
+                .bearer_auth(access_token)
+                .send()
+                .await?;
+            check_response(&response)?;
+
+            let signed_url: SignedUrlResponse = serde_json::from_str(&response.text().await?)?;
+            Ok(client.get(signed_url.url))
+        }
+    }
+}
+
+/// Check the status of a response to an authorized request.<br>
+/// A 401 or 403 status means the credentials were rejected, any other failure is a download error.
+pub fn check_response(response: &Response) -> errors::Result<()> {
+    match response.status() {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            Err(CredentialsRejected(response.url().to_string()))
+        }
+        status if !status.is_success() => Err(GameResourceDownloadError(format!("{:?}", response))),
+        _ => Ok(()),
+    }
+}
+
+/// Return a valid access token for the realm, refreshing it if it is about to expire.
+async fn get_access_token(app: &AppHandle, realm: &str) -> errors::Result<String> {
+    let credentials = load_credentials(app)?
+        .remove(realm)
+        .ok_or(CredentialsMissing(realm.to_string()))?;
+
+    let expires_soon = credentials
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now() + env::CREDENTIALS_REFRESH_MARGIN);
+    if !expires_soon {
+        return Ok(credentials.access_token);
+    }
+
+    let refreshed = refresh_credentials(app, realm, &credentials).await?;
+    let access_token = refreshed.access_token.to_owned();
+    set_credentials(app, realm, refreshed)?;
+    Ok(access_token)
+}
+
+/// Exchange the refresh token for a new access token.
+async fn refresh_credentials(
+    app: &AppHandle,
+    realm: &str,
+    credentials: &Credentials,
+) -> errors::Result<Credentials> {
+    let (Some(refresh_token), Some(token_endpoint)) =
+        (&credentials.refresh_token, &credentials.token_endpoint)
+    else {
+        return Err(CredentialsExpired(realm.to_string()));
+    };
+
+    info!("Refreshing the credentials of realm {}", realm);
+    let response = http::get_client(app)
+        .post(token_endpoint)
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token.as_str()),
+        ])
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        // The refresh token itself is expired or revoked, the player must log in again
+        return Err(CredentialsExpired(realm.to_string()));
+    }
+
+    let token: TokenResponse = serde_json::from_str(&response.text().await?)?;
+    Ok(Credentials {
+        access_token: token.access_token,
+        refresh_token: token.refresh_token.or(credentials.refresh_token.to_owned()),
+        expires_at: token.expires_in.map(|expires_in| now() + expires_in),
+        token_endpoint: credentials.token_endpoint.to_owned(),
+    })
+}
+
+fn load_credentials(app: &AppHandle) -> errors::Result<HashMap<String, Credentials>> {
+    let store = app
+        .store(env::STORE_FILE_NAME)
+        .map_err(|e| StoreAccessError(e.to_string()))?;
+    match store.get(env::STORE_CREDENTIALS_KEY) {
+        Some(value) => Ok(serde_json::from_value(value)?),
+        None => Ok(HashMap::new()),
+    }
+}
+
+/// Save the credentials of a realm, replacing the previous ones.
+pub fn set_credentials(
+    app: &AppHandle,
+    realm: &str,
+    credentials: Credentials,
+) -> errors::Result<()> {
+    let mut all_credentials = load_credentials(app)?;
+    all_credentials.insert(realm.to_string(), credentials);
+    save_credentials(app, &all_credentials)
+}
+
+/// Forget the credentials of a realm.
+pub fn remove_credentials(app: &AppHandle, realm: &str) -> errors::Result<()> {
+    let mut all_credentials = load_credentials(app)?;
+    all_credentials.remove(realm);
+    save_credentials(app, &all_credentials)
+}
+
+fn save_credentials(
+    app: &AppHandle,
+    all_credentials: &HashMap<String, Credentials>,
+) -> errors::Result<()> {
+    let store = app
+        .store(env::STORE_FILE_NAME)
+        .map_err(|e| StoreAccessError(e.to_string()))?;
+    store.set(
+        env::STORE_CREDENTIALS_KEY,
+        serde_json::to_value(all_credentials)?,
+    );
+    Ok(())
+}
+
+/// Return the current unix timestamp in seconds.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
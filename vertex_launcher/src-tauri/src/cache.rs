@@ -0,0 +1,179 @@
+use crate::games::Game;
+use crate::settings::ArchiveCacheSettings;
+use crate::{env, errors, settings};
+use log::{error, info};
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+
+/// # Archive cache module
+/// By default, an archive is deleted as soon as it has been extracted and verified.<br>
+/// When the player enables the archive cache in the settings, the archives are kept in the
+/// `env::ARCHIVE_CACHE_FOLDER` of the app data directory instead, so a game can be reinstalled or
+/// repaired without downloading it again. The least recently used archives are evicted once the
+/// cache grows over its size limit. The last use of an archive is tracked by its modification time.
+
+/// Return the cached archive of the latest revision of the game, if the cache is enabled and has it.<br>
+/// A cached archive whose size differs from the one declared by the catalog is evicted.
+pub fn get_cached_archive(app: &AppHandle, game: &Game) -> Option<PathBuf> {
+    if !settings::load_settings(app).archive_cache.keep_archives {
+        return None;
+    }
+    let cached_archive = get_cached_archive_path(app, game).ok()?;
+    let size = fs::metadata(&cached_archive)
+        .ok()
+        .filter(|metadata| metadata.is_file())?
+        .len();
+    if game
+        .game_archive
+        .download_size
+        .is_some_and(|download_size| download_size != size)
+    {
+        info!(
+            "The cached archive \"{}\" is incomplete or corrupted",
+            cached_archive.display()
+        );
+        evict_archive(&cached_archive);
+        return None;
+    }
+
+    if let Err(e) = mark_as_used(&cached_archive) {
+        error!(
+            "Error updating the last use of \"{}\": {:?}",
+            cached_archive.display(),
+            e
+        );
+    }
+    Some(cached_archive)
+}
+
+/// Dispose of an archive once extracted: move it to the cache if enabled, delete it otherwise.
+pub fn store_archive(app: &AppHandle, game: &Game, archive_path: &Path) -> errors::Result<()> {
+    let cache_settings = settings::load_settings(app).archive_cache;
+    if !cache_settings.keep_archives {
+        fs::remove_file(archive_path)?;
+        return Ok(());
+    }
+
+    let cached_archive = get_cached_archive_path(app, game)?;
+    if let Some(parent) = cached_archive.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(archive_path, &cached_archive).is_err() {
+        // The cache is on another volume than the game
+        fs::copy(archive_path, &cached_archive)?;
+        fs::remove_file(archive_path)?;
+    }
+    mark_as_used(&cached_archive)?;
+
+    apply_settings(app, &cache_settings);
+    Ok(())
+}
+
+/// Delete a cached archive that can't be used, e.g. because its extraction failed.
+pub fn evict_archive(archive_path: &Path) {
+    info!(
+        "Evicting \"{}\" from the archive cache",
+        archive_path.display()
+    );
+    if let Err(e) = fs::remove_file(archive_path) {
+        error!(
+            "Error evicting \"{}\" from the archive cache: {:?}",
+            archive_path.display(),
+            e
+        );
+    }
+}
+
+/// Apply the cache settings on the cached archives: empty the cache if it is disabled,
+/// otherwise evict the least recently used archives until the cache fits in its size limit.
+pub fn apply_settings(app: &AppHandle, cache_settings: &ArchiveCacheSettings) {
+    let cache_folder = match app.path().app_data_dir() {
+        Ok(app_data_dir) => app_data_dir.join(env::ARCHIVE_CACHE_FOLDER),
+        Err(e) => {
+            error!("Error getting the app data directory: {:?}", e);
+            return;
+        }
+    };
+    let max_size = if cache_settings.keep_archives {
+        cache_settings.max_size
+    } else {
+        0
+    };
+
+    if let Err(e) = evict(&cache_folder, max_size) {
+        error!("Error evicting archives from the cache: {:?}", e);
+    }
+}
+
+/// Delete the least recently used archives until the cache is not bigger than `max_size`.
+fn evict(cache_folder: &Path, max_size: u64) -> io::Result<()> {
+    if !cache_folder.is_dir() {
+        return Ok(());
+    }
+
+    let mut archives = Vec::new();
+    for entry in fs::read_dir(cache_folder)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            archives.push((entry.path(), metadata.len(), metadata.modified()?));
+        }
+    }
+
+    let mut cache_size: u64 = archives.iter().map(|(_, size, _)| size).sum();
+    archives.sort_by_key(|(_, _, last_use)| *last_use);
+    for (path, size, _) in archives {
+        if cache_size <= max_size {
+            break;
+        }
+        info!("Evicting \"{}\" from the archive cache", path.display());
+        fs::remove_file(&path)?;
+        cache_size -= size;
+    }
+    Ok(())
+}
+
+/// Rename the cached archive of the latest revision of the game, named after its title by older launcher versions.<br>
+/// The archives of past revisions can't be used anymore, they are evicted with the least recently used ones.
+pub fn migrate_legacy_archive(app_data_dir: &Path, game: &Game) -> io::Result<()> {
+    let cache_folder = app_data_dir.join(env::ARCHIVE_CACHE_FOLDER);
+    let legacy_archive =
+        cache_folder.join(get_archive_file_name(game, &game.get_legacy_folder_name()));
+    let cached_archive = cache_folder.join(get_archive_file_name(game, &game.get_folder_name()));
+    if legacy_archive.is_file() && !cached_archive.exists() {
+        fs::rename(&legacy_archive, &cached_archive)?;
+    }
+    Ok(())
+}
+
+/// Return the path of the archive of the latest revision of the game in the cache.
+fn get_cached_archive_path(app: &AppHandle, game: &Game) -> errors::Result<PathBuf> {
+    Ok(app
+        .path()
+        .app_data_dir()?
+        .join(env::ARCHIVE_CACHE_FOLDER)
+        .join(get_archive_file_name(game, &game.get_folder_name())))
+}
+
+/// Return the name of the archive of the latest revision of the game in the cache, prefixed by its folder name.
+fn get_archive_file_name(game: &Game, folder_name: &str) -> String {
+    let link = &game.game_archive.link;
+    // Only keep the file name of the link, the cache is a flat folder
+    let file_name = Path::new(&link.name)
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{}_{}_{}", folder_name, link.revision, file_name)
+}
+
+/// Record the archive as the most recently used one.
+fn mark_as_used(archive_path: &Path) -> io::Result<()> {
+    File::options()
+        .write(true)
+        .open(archive_path)?
+        .set_modified(SystemTime::now())
+}
@@ -1,19 +1,26 @@
-use crate::download::DownloadSteps::Downloading;
-use crate::download::GameDownload;
-use crate::env::{LOCAL_GAME_LIST, UPDATE_RATE};
+use crate::auth::Credentials;
+use crate::download::{download_file, DownloadSteps, GameDownload};
+use crate::env::{DOWNLOAD_LOCK, LOCAL_GAME_LIST, RUNNING_GAMES};
 use crate::errors::Verror;
-use crate::errors::Verror::{GameLaunchError, GameListFetchError, MessageError};
-use crate::games::Game;
-use crate::{env, errors};
-use futures_util::stream::StreamExt;
-use log::info;
+use crate::errors::Verror::{
+    GameImportError, GameLaunchError, GameListFetchError, GameMoveError, GameRepairError,
+    GameResourceDownloadError, GameRollbackError, GameUninstallError, GameUpdateError,
+    GameVerificationError, MessageError, UnsafePathError,
+};
+use crate::games::{Game, GameUpdate};
+use crate::http::HttpClient;
+use crate::install_manifest::InstallManifest;
+use crate::settings::{Settings, UpdatePolicy};
+use crate::{
+    archive, auth, cache, env, errors, import, install, library, manifest, patch, paths, queue,
+    repair, settings,
+};
+use log::{error, info, warn};
 use std::fs;
-use std::io::Cursor;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
-use tauri::{Emitter, Manager};
+use tauri::{Emitter, State};
 use tauri_plugin_http::reqwest::header::ACCEPT;
-use tauri_plugin_http::reqwest::Client;
 use tauri_plugin_store::StoreExt;
 
 /// # Commands module
@@ -121,6 +128,8 @@ pub async fn get_game(game: u8) -> Result<String, Verror> {
 
 /// ## Download command
 /// **Description**: Download a file from the internet.<br>
+/// Downloads are queued and run one after another. The promise resolves once this game is installed.
+/// A game that is already queued is refused, its first download installs it.<br>
 /// **Frontend usage**:
 /// ```typescript
 /// invoke('download', {game: id})
@@ -135,13 +144,785 @@ pub async fn get_game(game: u8) -> Result<String, Verror> {
 /// **Parameters**:<br>
 /// NAME (TYPE)\[SOURCE]: DESCRIPTION
 /// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
-/// - game (u8)\[FrontEnd]: The id of the game to download.
+/// - game (u8)\[FrontEnd]: The id of the game to download.<br>
+/// - library (Option<PathBuf>)\[FrontEnd]: The library folder to install the game in, one of the app data directory
+///   and the `library_folders` of the settings. Ignored for installed games, which are updated where they are.
+///   If omitted, the game is installed in the library it was last located in (the app data directory by default).
 #[tauri::command]
-pub async fn download(app_handle: tauri::AppHandle, game: u8) -> errors::Result<()> {
+pub async fn download(
+    app_handle: tauri::AppHandle,
+    game: u8,
+    library: Option<PathBuf>,
+) -> errors::Result<()> {
+    // A game already queued is installed by the download that queued it
+    let already_queued =
+        || GameResourceDownloadError(format!("{}: the game is already being downloaded", game));
+    if queue::is_queued(game) {
+        return Err(already_queued());
+    }
+    if let Some(library) = library {
+        set_install_location(&app_handle, game, &library).await?;
+    }
+
+    // Queue the download and wait for the previous ones to end.
+    // The queue is saved in the store so an interrupted download can be resumed at the next startup.
+    if !queue::enqueue(&app_handle, game) {
+        return Err(already_queued());
+    }
+    run_queued_download(&app_handle, game).await
+}
+
+/// ## Install version command
+/// **Description**: Install a past version of a game listed by the catalog, through the download queue.<br>
+/// The game is pinned to this version: it is never updated automatically, and downloading it again reinstalls the same
+/// version, until `unpin_game` is called. The installed version is reported by the `installed_version` field of the game,
+/// separately from its latest `version`. Choosing the latest version installs it and unpins the game.
+/// When the install fails, the game keeps its previous pin.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('install_version', {game: id, version: "1.0.2"})
+/// .then(() => {
+///   // version installed
+/// })
+/// .catch((error) => {
+///  console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - game (u8)\[FrontEnd]: The id of the game.<br>
+/// - version (String)\[FrontEnd]: The version to install, the latest one or one of the `versions` of the game archive.<br>
+/// - library (Option<PathBuf>)\[FrontEnd]: The library folder to install the game in, see the download command.
+#[tauri::command]
+pub async fn install_version(
+    app_handle: tauri::AppHandle,
+    game: u8,
+    version: String,
+    library: Option<PathBuf>,
+) -> errors::Result<()> {
+    if queue::is_queued(game) {
+        return Err(GameUpdateError(format!(
+            "{}: the game is being downloaded",
+            game
+        )));
+    }
+    if RUNNING_GAMES.lock().unwrap().contains(&game) {
+        return Err(GameUpdateError(format!("{}: the game is running", game)));
+    }
+
+    // The pinned revision is saved before queuing the download, so an interrupted install resumes at the same version
+    let (revision, previous_pinned_revision) = {
+        let mut game_list = LOCAL_GAME_LIST.write().await;
+        let update_local_game = game_list.get_mut(&game).ok_or(GameListFetchError(format!(
+            "Game with id {} not found",
+            game
+        )))?;
+        let game_archive = &update_local_game.game_archive;
+        let revision = if version == update_local_game.version {
+            game_archive.link.revision
+        } else {
+            game_archive
+                .versions
+                .iter()
+                .find(|archive_version| archive_version.version == version)
+                .map(|archive_version| archive_version.link.revision)
+                .ok_or(GameUpdateError(format!(
+                    "{}: the version {} is not listed by the catalog",
+                    game, version
+                )))?
+        };
+        let previous_pinned_revision = update_local_game.pinned_revision;
+        update_local_game.pinned_revision = Some(revision);
+        (revision, previous_pinned_revision)
+    };
+    save_game_list(&app_handle).await?;
+
+    // Verror isn't Send, only its message is kept while the pin is restored
+    let result = download(app_handle.clone(), game, library)
+        .await
+        .map_err(|e| MessageError(e.to_string()));
+    if result.is_err() {
+        // The version isn't installed, the game is pinned as it was before
+        {
+            let mut game_list = LOCAL_GAME_LIST.write().await;
+            if let Some(update_local_game) = game_list
+                .get_mut(&game)
+                .filter(|local_game| local_game.pinned_revision == Some(revision))
+            {
+                update_local_game.pinned_revision = previous_pinned_revision;
+            }
+        }
+        save_game_list(&app_handle).await?;
+    }
+    result
+}
+
+/// Wait for the previous downloads to end, then install the queued game and remove it from the queue.
+pub async fn run_queued_download(app_handle: &tauri::AppHandle, game: u8) -> errors::Result<()> {
+    let result = {
+        let _active_download = DOWNLOAD_LOCK.lock().await;
+        install_game(app_handle, game).await
+    };
+    queue::dequeue(app_handle, game);
+    result
+}
+
+///## Get updates command
+/// **Description**: Get the updates available for the installed games, found when the catalog was fetched on startup.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('get_updates')
+/// .then((updates) => {
+///    let gameUpdates = JSON.parse(updates);
+///    // e.g. [{game_id: 1, title: "...", installed_version: "1.0.0", version: "1.1.0", installed_revision: 3, revision: 4, download_size: 10485760, installed_size: 2147483648}]
+/// })
+/// .catch((error) => {
+///   console.error(error);
+/// });
+/// ```
+///
+/// **Returns**:
+/// - Result<String, Verror>: JSON string of the available updates, ordered like the games list.
+///   The download size is null when unknown (e.g. for games updated from a content manifest).
+#[tauri::command]
+pub async fn get_updates() -> Result<String, Verror> {
+    let game_list = LOCAL_GAME_LIST.read().await;
+
+    let mut games: Vec<&Game> = game_list.values().collect();
+    games.sort_by(|a, b| a.weight.cmp(&b.weight).reverse());
+    let updates: Vec<GameUpdate> = games.iter().filter_map(|game| game.get_update()).collect();
+
+    Ok(serde_json::to_string(&updates)?)
+}
+
+///## Update game command
+/// **Description**: Update an installed game to its latest revision, through the download queue.<br>
+/// The paths preserved by the player (see `set_preserved_paths`) are kept as they are.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('update_game', {game: id})
+/// .then(() => {
+///   // game updated
+/// })
+/// .catch((error) => {
+///  console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - game (u8)\[FrontEnd]: The id of the game to update.
+#[tauri::command]
+pub async fn update_game(app_handle: tauri::AppHandle, game: u8) -> errors::Result<()> {
+    let has_update = {
+        let game_list = LOCAL_GAME_LIST.read().await;
+        game_list
+            .get(&game)
+            .ok_or(GameListFetchError(format!(
+                "Game with id {} not found",
+                game
+            )))?
+            .get_update()
+            .is_some()
+    };
+    if !has_update {
+        return Err(GameUpdateError(format!("{}: no update is available", game)));
+    }
+
+    download(app_handle, game, None).await
+}
+
+///## Update all command
+/// **Description**: Update every installed game that has an update available, one after another through the download queue.<br>
+/// A failed update doesn't stop the next ones.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('update_all')
+/// .then(() => {
+///   // every game updated
+/// })
+/// .catch((error) => {
+///  console.error(error); // lists the games that failed to update
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.
+#[tauri::command]
+pub async fn update_all(app_handle: tauri::AppHandle) -> errors::Result<()> {
+    // Games already queued are updated by their own download
+    let games: Vec<u8> = {
+        let game_list = LOCAL_GAME_LIST.read().await;
+        let mut games: Vec<&Game> = game_list
+            .values()
+            .filter(|game| game.get_update().is_some())
+            .collect();
+        games.sort_by(|a, b| a.weight.cmp(&b.weight).reverse());
+        games.iter().map(|game| game.id).collect()
+    };
+
+    // Queue them all first so the frontend knows about every pending update
+    let games: Vec<u8> = games
+        .into_iter()
+        .filter(|game| queue::enqueue(&app_handle, *game))
+        .collect();
+    info!("Updating {} game(s)", games.len());
+    // The errors are kept as text as they can't be kept across an await point
+    let mut failed_updates = Vec::new();
+    for game in games {
+        if let Err(e) = run_queued_download(&app_handle, game).await {
+            error!("Error updating game {}: {:?}", game, e);
+            failed_updates.push(format!("{} ({})", game, e));
+        }
+    }
+
+    if !failed_updates.is_empty() {
+        return Err(GameUpdateError(failed_updates.join(", ")));
+    }
+    Ok(())
+}
+
+///## Set preserved paths command
+/// **Description**: Declare the paths of a game that updates and repairs must never overwrite nor delete,
+/// such as its saves or its configuration files. The archive version of a preserved path is only installed if it is missing.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('set_preserved_paths', {game: id, paths: ["saves", "config/settings.ini"]})
+/// .then(() => {
+///   // paths saved
+/// })
+/// .catch((error) => {
+///  console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - game (u8)\[FrontEnd]: The id of the game.<br>
+/// - paths (Vec<String>)\[FrontEnd]: The files or folders to preserve, relative to the install folder. Replaces the previous ones.
+#[tauri::command]
+pub async fn set_preserved_paths(
+    app_handle: tauri::AppHandle,
+    game: u8,
+    paths: Vec<String>,
+) -> errors::Result<()> {
+    // Store the paths in the format of the install manifests, e.g. "config/settings.ini"
+    let mut preserved_paths = Vec::with_capacity(paths.len());
+    for path in paths {
+        let relative_path = crate::paths::join_inside(Path::new(""), &path)?;
+        let preserved_path = relative_path.to_string_lossy().replace('\\', "/");
+        if preserved_path.is_empty() {
+            return Err(UnsafePathError(path));
+        }
+        preserved_paths.push(preserved_path);
+    }
+
+    {
+        let mut game_list = LOCAL_GAME_LIST.write().await;
+        let update_local_game = game_list.get_mut(&game).ok_or(GameListFetchError(format!(
+            "Game with id {} not found",
+            game
+        )))?;
+        update_local_game.preserved_paths = preserved_paths;
+    }
+    save_game_list(&app_handle).await
+}
+
+///## Set update policy command
+/// **Description**: Choose what to do when an update is found on startup for a game: queue it ("automatic"),
+/// show a notification ("notify") or nothing ("manual"). The default policy of every game is in the settings.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('set_update_policy', {game: id, policy: "automatic"})
+/// .then(() => {
+///   // policy saved
+/// })
+/// .catch((error) => {
+///  console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - game (u8)\[FrontEnd]: The id of the game.<br>
+/// - policy (Option<UpdatePolicy>)\[FrontEnd]: The update policy of the game, null to follow the one of the settings.
+#[tauri::command]
+pub async fn set_update_policy(
+    app_handle: tauri::AppHandle,
+    game: u8,
+    policy: Option<UpdatePolicy>,
+) -> errors::Result<()> {
+    {
+        let mut game_list = LOCAL_GAME_LIST.write().await;
+        let update_local_game = game_list.get_mut(&game).ok_or(GameListFetchError(format!(
+            "Game with id {} not found",
+            game
+        )))?;
+        update_local_game.update_policy = policy;
+    }
+    save_game_list(&app_handle).await
+}
+
+///## Rollback game command
+/// **Description**: Restore the revision a game had before its last update, when the `keep_previous_version` setting
+/// was on during the update. The files that are not part of the game (e.g. its saves) and the preserved paths are kept.<br>
+/// The game is then pinned to the restored revision: it is no longer updated automatically until `unpin_game` is called.
+/// The game can't be rolled back while it is running or downloading.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('rollback_game', {game: id})
+/// .then(() => {
+///   // previous revision restored
+/// })
+/// .catch((error) => {
+///  console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - game (u8)\[FrontEnd]: The id of the game to roll back.
+#[tauri::command]
+pub async fn rollback_game(app_handle: tauri::AppHandle, game: u8) -> errors::Result<()> {
+    if RUNNING_GAMES.lock().unwrap().contains(&game) {
+        return Err(GameRollbackError(format!("{}: the game is running", game)));
+    }
+    let _active_download = DOWNLOAD_LOCK.lock().await;
+    if queue::is_queued(game) {
+        return Err(GameRollbackError(format!(
+            "{}: the game is being downloaded",
+            game
+        )));
+    }
+
+    // 1 - Get the previous revision kept for the game
+    let local_game = {
+        let game_list = LOCAL_GAME_LIST.read().await;
+        game_list
+            .get(&game)
+            .ok_or(GameListFetchError(format!(
+                "Game with id {} not found",
+                game
+            )))?
+            .to_owned()
+    };
+    let install_folder = local_game.get_install_folder(&app_handle)?;
+    let rollback_revision = match local_game.rollback_revision {
+        Some(revision)
+            if local_game.game_archive.link.local_path.is_some()
+                && install::get_rollback_folder(&install_folder).exists() =>
+        {
+            revision
+        }
+        _ => {
+            return Err(GameRollbackError(format!(
+                "{}: no previous revision is kept",
+                game
+            )))
+        }
+    };
+
+    // 2 - Swap the previous revision in place of the current one
+    info!(
+        "Rolling back {} to revision {}",
+        local_game.title, rollback_revision
+    );
+    install::restore_previous_install(&local_game, &install_folder)?;
+    // The content manifest belongs to the newer revision, the next update compares the files with their hashes instead
+    manifest::remove_installed_manifest(&app_handle, &local_game)?;
+    let executable_path = install::make_game_executable(&local_game, &install_folder)?;
+
+    // 3 - Pin the game to the restored revision
+    {
+        let mut game_list = LOCAL_GAME_LIST.write().await;
+        let update_local_game = game_list.get_mut(&game).ok_or(GameListFetchError(format!(
+            "Game with id {} not found",
+            game
+        )))?;
+        update_local_game.set_installed(executable_path, rollback_revision);
+        update_local_game.installed_version = local_game.rollback_version.to_owned();
+        update_local_game.pinned_revision = Some(rollback_revision);
+        update_local_game.rollback_revision = None;
+        update_local_game.rollback_version = None;
+    }
+    save_game_list(&app_handle).await
+}
+
+///## Unpin game command
+/// **Description**: Let a game pinned to its installed revision (e.g. by `rollback_game`) be updated again.
+/// Its update policy applies from the next startup.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('unpin_game', {game: id})
+/// .then(() => {
+///   // game unpinned
+/// })
+/// .catch((error) => {
+///  console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - game (u8)\[FrontEnd]: The id of the game to unpin.
+#[tauri::command]
+pub async fn unpin_game(app_handle: tauri::AppHandle, game: u8) -> errors::Result<()> {
+    {
+        let mut game_list = LOCAL_GAME_LIST.write().await;
+        let update_local_game = game_list.get_mut(&game).ok_or(GameListFetchError(format!(
+            "Game with id {} not found",
+            game
+        )))?;
+        update_local_game.pinned_revision = None;
+    }
+    save_game_list(&app_handle).await
+}
+
+/// Remember the library folder a game is going to be installed in, unless the game is already installed.
+async fn set_install_location(
+    app_handle: &tauri::AppHandle,
+    game: u8,
+    library: &Path,
+) -> errors::Result<()> {
+    let install_location = library::get_install_location(app_handle, library)?;
+    {
+        let mut game_list = LOCAL_GAME_LIST.write().await;
+        let update_local_game = game_list.get_mut(&game).ok_or(GameListFetchError(format!(
+            "Game with id {} not found",
+            game
+        )))?;
+        if update_local_game.game_archive.link.local_path.is_some() {
+            return Ok(());
+        }
+        update_local_game.install_location = install_location;
+    }
+    save_game_list(app_handle).await
+}
+
+/// Download and install the latest revision of a game, then save it to the local game list.
+async fn install_game(app_handle: &tauri::AppHandle, game: u8) -> errors::Result<()> {
     info!("Downloading game {}", game);
     let mut download = GameDownload::new(game, app_handle.clone());
 
-    // 1- Get the game from the local game list
+    // 1- Get the game from the local game list
+    let local_game = {
+        let game_list = LOCAL_GAME_LIST.read().await;
+        game_list
+            .get(&game)
+            .ok_or(GameListFetchError(format!(
+                "Game with id {} not found",
+                game
+            )))?
+            .to_owned()
+    };
+    // A game pinned to a past version of the catalog is installed at that version instead of the latest one
+    let pinned_version = local_game.get_pinned_version().cloned();
+    let local_game = match &pinned_version {
+        Some(version) => {
+            info!("Installing version {} of game {}", version.version, game);
+            Game {
+                game_archive: local_game.game_archive.get_version_archive(version),
+                ..local_game
+            }
+        }
+        None => local_game,
+    };
+
+    // Don't download an archive that can't be extracted
+    if local_game.game_archive.need_extract {
+        local_game.game_archive.get_archive_format()?;
+    }
+
+    // 2- create the folder to store the downloaded file
+    let game_data_folder = local_game.get_install_folder(app_handle)?;
+    fs::create_dir_all(&game_data_folder)?;
+
+    // Keep the installed revision aside if the player wants to be able to roll back the update
+    let keep_previous = settings::load_settings(app_handle).keep_previous_version
+        && local_game.game_archive.link.local_path.is_some()
+        && local_game
+            .game_archive
+            .installed_revision
+            .is_some_and(|revision| revision != local_game.game_archive.link.revision);
+
+    // Ensure the volume has room for the archive and the extracted files before starting
+    match local_game.game_archive.get_required_space(keep_previous) {
+        Some(required_space) => {
+            let available_space = fs4::available_space(&game_data_folder)?;
+            if required_space > available_space {
+                return Err(Verror::NotEnoughDiskSpace {
+                    required: required_space,
+                    available: available_space,
+                });
+            }
+        }
+        None => warn!(
+            "The catalog doesn't declare the sizes of game {}, the free disk space is not checked",
+            game
+        ),
+    }
+
+    let start_time = Instant::now();
+    download.set_start_time(start_time);
+
+    // Updates made in place need a copy of the installed revision, the other ones move it aside when swapping
+    if keep_previous
+        && (local_game.game_archive.manifest_url.is_some()
+            || local_game.game_archive.get_patch_chain().is_some())
+    {
+        install::keep_previous_install(&local_game, &game_data_folder)?;
+    }
+
+    // 3 - Games published with a content manifest only download the files that changed.
+    // Otherwise, update the installed files with the published deltas if a chain exists from the installed revision.
+    // If none of them can be used, fall back to a full download.
+    let mut updated_in_place = false;
+    if let Some(manifest_url) = &local_game.game_archive.manifest_url {
+        let content_manifest = manifest::install_from_manifest(
+            app_handle,
+            &local_game,
+            manifest_url,
+            &game_data_folder,
+            &mut download,
+        )
+        .await?;
+        InstallManifest::from_content_manifest(game, &content_manifest)
+            .exclude_preserved_paths(&local_game)
+            .save(&game_data_folder)?;
+        updated_in_place = true;
+    } else if let Some(patches) = local_game.game_archive.get_patch_chain() {
+        match patch::apply_patch_chain(
+            app_handle,
+            &local_game,
+            &patches,
+            &game_data_folder,
+            &mut download,
+        )
+        .await
+        {
+            Ok(patched_files) => {
+                // Installs made without an install manifest are scanned once patched
+                let install_manifest = match InstallManifest::load(&game_data_folder) {
+                    Some(mut install_manifest) => {
+                        install_manifest.apply_patches(
+                            &game_data_folder,
+                            local_game.game_archive.link.revision,
+                            &patched_files,
+                        )?;
+                        install_manifest
+                    }
+                    None => InstallManifest::scan(
+                        &game_data_folder,
+                        &local_game,
+                        local_game.game_archive.link.revision,
+                    )?,
+                };
+                install_manifest
+                    .exclude_preserved_paths(&local_game)
+                    .save(&game_data_folder)?;
+                updated_in_place = true;
+            }
+            Err(e) => error!(
+                "Failed to patch game {}, falling back to a full download: {}",
+                game, e
+            ),
+        }
+    }
+
+    if !updated_in_place {
+        // 4 - Download the archive to a staging folder, the current install is left untouched until the new one is ready.
+        // Archives kept in the cache are extracted from there instead of being downloaded again.
+        let staging_folder = install::prepare_staging_folder(&game_data_folder)?;
+        let staged_archive_path =
+            paths::join_inside(&staging_folder, &local_game.game_archive.link.name)?;
+        let mut cached_archive = if local_game.game_archive.need_extract {
+            cache::get_cached_archive(app_handle, &local_game)
+        } else {
+            None
+        };
+        let archive_path = loop {
+            let archive_path = match cached_archive.take() {
+                Some(cached_archive) => {
+                    info!("Using the cached archive \"{}\"", cached_archive.display());
+                    cached_archive
+                }
+                None => {
+                    let request =
+                        auth::authorize_request(app_handle, &local_game.game_archive.link)
+                            .await?
+                            .header(ACCEPT, "application/octet-stream");
+                    download_file(request, &staged_archive_path, &mut download).await?;
+
+                    // download completed
+                    info!(
+                        "Download completed in {:.2} seconds",
+                        start_time.elapsed().as_secs_f64()
+                    );
+                    staged_archive_path.to_owned()
+                }
+            };
+
+            // 5 - Extract the archive to the staging folder.
+            // A cached archive that can't be extracted is evicted and the archive is downloaded again
+            if local_game.game_archive.need_extract {
+                download.set_steps(crate::download::DownloadSteps::Extracting);
+
+                if let Err(e) = archive::extract(
+                    &archive_path,
+                    local_game.game_archive.get_archive_format()?,
+                    &staging_folder,
+                    local_game.game_archive.strip_top_level_folder,
+                    None,
+                    local_game.game_archive.installed_size,
+                    &mut download,
+                ) {
+                    if archive_path == staged_archive_path {
+                        return Err(e);
+                    }
+                    error!(
+                        "Error extracting the cached archive of game {}, downloading it again: {}",
+                        game, e
+                    );
+                    cache::evict_archive(&archive_path);
+                    install::prepare_staging_folder(&game_data_folder)?;
+                    continue;
+                }
+            }
+            break archive_path;
+        };
+        install::verify_staged_install(
+            &local_game
+                .game_archive
+                .get_executable_path(&staging_folder)?,
+        )?;
+
+        // 6 - Once the extraction is verified, the downloaded archive is deleted or moved to the cache.
+        // Then swap the staging folder in, keeping the files that are not part of the archive
+        // (except the archive left in the install by older launcher versions) and the paths preserved by the player.
+        info!("Cleaning downloaded files");
+        download.set_steps(crate::download::DownloadSteps::Cleaning);
+        if local_game.game_archive.need_extract && archive_path == staged_archive_path {
+            cache::store_archive(app_handle, &local_game, &staged_archive_path)?;
+        }
+        install::drop_preserved_paths(&local_game, &game_data_folder, &staging_folder)?;
+        // Only the files of the archive are left in the staging folder at this point
+        InstallManifest::from_files(
+            &staging_folder,
+            game,
+            local_game.game_archive.link.revision,
+            &manifest::list_files(&staging_folder)?,
+        )?
+        .exclude_preserved_paths(&local_game)
+        .save(&staging_folder)?;
+        let moved_files = install::carry_over_files(
+            &game_data_folder,
+            &staging_folder,
+            &[PathBuf::from(&local_game.game_archive.link.name)],
+        )?;
+        install::swap_install(&staging_folder, &game_data_folder, keep_previous).inspect_err(
+            |_| install::put_back_files(&game_data_folder, &staging_folder, &moved_files),
+        )?;
+    }
+
+    // 7 - Make the game binaries executable, whatever the permissions stored in the archive
+    let executable_path = install::make_game_executable(&local_game, &game_data_folder)?;
+    // The content manifest of the latest revision doesn't describe the files of a past version
+    if pinned_version.is_some() {
+        manifest::remove_installed_manifest(app_handle, &local_game)?;
+    }
+
+    // 8 - Update the local game list with the downloaded file path and the installed revision
+    {
+        let mut game_list = LOCAL_GAME_LIST.write().await;
+        let update_local_game = game_list.get_mut(&game).ok_or(GameListFetchError(format!(
+            "Game with id {} not found",
+            game
+        )))?;
+        update_local_game.set_installed(executable_path, local_game.game_archive.link.revision);
+        match &pinned_version {
+            Some(version) => update_local_game.installed_version = Some(version.version.to_owned()),
+            None => update_local_game.pinned_revision = None,
+        }
+        if keep_previous {
+            update_local_game.rollback_revision = local_game.game_archive.installed_revision;
+            update_local_game.rollback_version = local_game.installed_version.to_owned();
+        }
+    }
+
+    // 9 - Update the local game list and give it to the frontend
+    save_game_list(app_handle).await?;
+
+    download.complete().await;
+    Ok(())
+}
+
+/// Save the local game list to the store and give it to the frontend.
+async fn save_game_list(app_handle: &tauri::AppHandle) -> errors::Result<()> {
+    {
+        let store = match app_handle.store(env::STORE_FILE_NAME) {
+            Ok(store) => store,
+            Err(e) => return Err(errors::Verror::StoreAccessError(e.to_string())),
+        };
+        let game_list = LOCAL_GAME_LIST.read().await;
+        store.set(
+            env::STORE_LOCAL_GAME_LIST_KEY,
+            serde_json::to_value(&*game_list).unwrap(),
+        );
+    }
+    // Use the get game list command to update the frontend ensuring the format is always the same for the frontend
+    app_handle.emit(env::EVENT_GAME_LIST_UPDATED, get_game_list().await?)?;
+    Ok(())
+}
+
+/// ## Uninstall command
+/// **Description**: Delete the installed files of a game. The game can't be uninstalled while it is running or downloading.<br>
+/// The game stays in the library with its resources, ready to be downloaded again.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('uninstall', {game: id, keepSaveData: true})
+/// .then(() => {
+///   // game uninstalled
+/// })
+/// .catch((error) => {
+///  console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - game (u8)\[FrontEnd]: The id of the game to uninstall.<br>
+/// - keep_save_data (bool)\[FrontEnd]: If true, only the files written by the launcher are deleted and the files
+///   created by the game (e.g. its saves) are kept. Refused for the installs whose game files are unknown, e.g. the ones
+///   made before install manifests existed.
+#[tauri::command]
+pub async fn uninstall(
+    app_handle: tauri::AppHandle,
+    game: u8,
+    keep_save_data: bool,
+) -> errors::Result<()> {
+    // 1 - Refuse to remove files that are in use.
+    // The queue is checked once no download is running, so none can start until the files are removed
+    if RUNNING_GAMES.lock().unwrap().contains(&game) {
+        return Err(GameUninstallError(format!("{}: the game is running", game)));
+    }
+    let _active_download = DOWNLOAD_LOCK.lock().await;
+    if queue::is_queued(game) {
+        return Err(GameUninstallError(format!(
+            "{}: the game is being downloaded",
+            game
+        )));
+    }
+
+    // 2 - Get the game from the local game list
     let local_game = {
         let game_list = LOCAL_GAME_LIST.read().await;
         game_list
@@ -152,125 +933,403 @@ pub async fn download(app_handle: tauri::AppHandle, game: u8) -> errors::Result<
             )))?
             .to_owned()
     };
+    if local_game.game_archive.link.local_path.is_none() {
+        return Err(GameUninstallError(format!(
+            "{}: the game is not installed",
+            game
+        )));
+    }
 
-    // 2- create the folder to store the downloaded file
-    let game_data_folder = app_handle
-        .path()
-        .app_data_dir()?
-        .join(local_game.get_folder_name());
-    let archive_path = game_data_folder.join(&local_game.game_archive.link.name);
-    fs::create_dir_all(&game_data_folder)?;
+    // 3 - Delete the installed files
+    info!("Uninstalling game {}", game);
+    let install_folder = local_game.get_install_folder(&app_handle)?;
+    if install_folder.exists() {
+        install::remove_install(&install_folder, &local_game, keep_save_data)?;
+    }
+    install::remove_previous_install(&install_folder)?;
+    manifest::remove_installed_manifest(&app_handle, &local_game)?;
 
-    // 3- Download the zip file
-    let start_time = Instant::now();
-    let client = Client::new();
-    let response = client
-        .get(&local_game.game_archive.link.url)
-        .header(ACCEPT, "application/octet-stream")
-        .send()
-        .await?;
+    // 4 - Mark the game as not installed
+    {
+        let mut game_list = LOCAL_GAME_LIST.write().await;
+        let update_local_game = game_list.get_mut(&game).ok_or(GameListFetchError(format!(
+            "Game with id {} not found",
+            game
+        )))?;
+        update_local_game.game_archive.link.local_path = None;
+        update_local_game.game_archive.installed_revision = None;
+        update_local_game.game_archive.need_update = false;
+        update_local_game.install_location = None;
+        update_local_game.installed_version = None;
+        update_local_game.pinned_revision = None;
+        update_local_game.rollback_revision = None;
+        update_local_game.rollback_version = None;
+    }
 
-    let total_size = response
-        .content_length()
-        .ok_or(MessageError("Failed to get the content length".to_string()))?;
+    // 5 - Update the local game list and give it to the frontend
+    save_game_list(&app_handle).await
+}
 
-    download.set_file_size(total_size);
-    download.set_start_time(start_time);
-    download.set_steps(Downloading);
+/// Return the installed game and its install folder.
+async fn get_installed_game(
+    app_handle: &tauri::AppHandle,
+    game: u8,
+) -> errors::Result<(Game, PathBuf)> {
+    let local_game = {
+        let game_list = LOCAL_GAME_LIST.read().await;
+        game_list
+            .get(&game)
+            .ok_or(GameListFetchError(format!(
+                "Game with id {} not found",
+                game
+            )))?
+            .to_owned()
+    };
+    if local_game.game_archive.link.local_path.is_none() {
+        return Err(GameVerificationError(format!(
+            "{}: the game is not installed",
+            game
+        )));
+    }
+    let install_folder = local_game.get_install_folder(app_handle)?;
+    Ok((local_game, install_folder))
+}
+
+/// ## Verify game command
+/// **Description**: Check the installed files of a game against their expected size and hash.<br>
+/// The game can't be verified while it is queued for download, and the verification waits for the active download to end.
+/// The progress is broadcast through the download progress event of the game, with the Verifying step.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('verify_game', {game: id})
+/// .then((report) => {
+///   const {missing, modified, extra, unverifiable} = JSON.parse(report);
+/// })
+/// .catch((error) => {
+///  console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - game (u8)\[FrontEnd]: The id of the game to verify.
+///
+/// **Returns**:
+/// - Result<String, Verror>: JSON string of the report listing the missing, modified, extra and unverifiable files
+///   (paths relative to the install folder), or an error if the installed files are unknown.
+///   The changed files of installs only known from a scan are unverifiable, they can belong to the player.
+#[tauri::command]
+pub async fn verify_game(app_handle: tauri::AppHandle, game: u8) -> Result<String, Verror> {
+    let _active_download = DOWNLOAD_LOCK.lock().await;
+    if queue::is_queued(game) {
+        return Err(GameVerificationError(format!(
+            "{}: the game is being downloaded",
+            game
+        )));
+    }
+    let (local_game, install_folder) = get_installed_game(&app_handle, game).await?;
+    let expected_files = repair::get_expected_files(&app_handle, &local_game, &install_folder)?;
+
+    let mut download = GameDownload::new(game, app_handle.clone());
+    let report =
+        repair::verify_install(&install_folder, &local_game, &expected_files, &mut download)?;
+    download.set_steps(DownloadSteps::Complete);
 
-    let mut last_update = Instant::now() - std::time::Duration::from_millis(UPDATE_RATE as u64);
+    Ok(serde_json::to_string(&report)?)
+}
 
-    let mut bytes: Vec<u8> = Vec::with_capacity(total_size as usize);
-    let mut downloaded: u64 = 0;
-    let mut stream = response.bytes_stream();
+/// ## Repair game command
+/// **Description**: Verify the installed files of a game, then download again the missing and modified ones.<br>
+/// Extra files (e.g. saves) are left untouched. The game can't be repaired while it is running or queued for download,
+/// and the repair waits for the active download to end. The progress is broadcast through the download progress event of the game.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('repair_game', {game: id})
+/// .then((report) => {
+///   // files repaired, the report lists the files that were broken
+/// })
+/// .catch((error) => {
+///  console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - game (u8)\[FrontEnd]: The id of the game to repair.
+///
+/// **Returns**:
+/// - Result<String, Verror>: JSON string of the verification report made before the repair.
+#[tauri::command]
+pub async fn repair_game(app_handle: tauri::AppHandle, game: u8) -> Result<String, Verror> {
+    if RUNNING_GAMES.lock().unwrap().contains(&game) {
+        return Err(GameRepairError(format!("{}: the game is running", game)));
+    }
+    let _active_download = DOWNLOAD_LOCK.lock().await;
+    if queue::is_queued(game) {
+        return Err(GameRepairError(format!(
+            "{}: the game is being downloaded",
+            game
+        )));
+    }
 
-    while let Some(stream_item) = stream.next().await {
-        let chunk = stream_item.or(Err(MessageError(format!(
-            "Failed to get a chunk for game {}",
-            &local_game.title
-        ))))?;
+    // 1 - Find the broken files
+    let (local_game, install_folder) = get_installed_game(&app_handle, game).await?;
+    let expected_files = repair::get_expected_files(&app_handle, &local_game, &install_folder)?;
+    let mut download = GameDownload::new(game, app_handle.clone());
+    let report =
+        repair::verify_install(&install_folder, &local_game, &expected_files, &mut download)?;
 
-        downloaded = std::cmp::min(downloaded + (chunk.len() as u64), total_size);
+    // 2 - Fetch them again
+    repair::repair_install(
+        &app_handle,
+        &local_game,
+        &install_folder,
+        &expected_files,
+        &report,
+        &mut download,
+    )
+    .await?;
+    install::make_game_executable(&local_game, &install_folder)?;
 
-        bytes.append(&mut chunk.to_vec());
+    // Installs made before install manifests existed get one, now that their files are known to be right
+    if InstallManifest::load(&install_folder).is_none() {
+        expected_files.save(&install_folder)?;
+    }
+    download.set_steps(DownloadSteps::Complete);
 
-        download.update(downloaded, None);
+    Ok(serde_json::to_string(&report)?)
+}
 
-        if (last_update.elapsed().as_millis() as u16) < UPDATE_RATE {
-            // don't advertise the download progress too often
-            continue;
-        }
-        // Advertise the download progress
-        download.advertise();
-        info!("Download progress: {}", download.get_state());
+/// ## Move game command
+/// **Description**: Move an installed game to another library folder, without downloading it again.<br>
+/// Across drives, the files are copied and verified before the source is deleted. The install manifests only hold
+/// relative paths, so only the executable path of the game changes. The previous revision kept for a rollback is deleted.
+/// The game can't be moved while it is running,
+/// and the move waits for the active download to end. The progress is broadcast through the download progress event of the game.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('move_game', {game: id, library: "D:\\Games"})
+/// .then(() => {
+///   // game moved
+/// })
+/// .catch((error) => {
+///  console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - game (u8)\[FrontEnd]: The id of the game to move.<br>
+/// - library (PathBuf)\[FrontEnd]: The library folder to move the game to, one of the app data directory and the
+///   `library_folders` of the settings.
+#[tauri::command]
+pub async fn move_game(
+    app_handle: tauri::AppHandle,
+    game: u8,
+    library: PathBuf,
+) -> errors::Result<()> {
+    if RUNNING_GAMES.lock().unwrap().contains(&game) {
+        return Err(GameMoveError(format!("{}: the game is running", game)));
+    }
+    let _active_download = DOWNLOAD_LOCK.lock().await;
 
-        last_update = Instant::now();
+    // 1 - Get the game from the local game list
+    let local_game = {
+        let game_list = LOCAL_GAME_LIST.read().await;
+        game_list
+            .get(&game)
+            .ok_or(GameListFetchError(format!(
+                "Game with id {} not found",
+                game
+            )))?
+            .to_owned()
+    };
+    if local_game.game_archive.link.local_path.is_none() {
+        return Err(GameMoveError(format!(
+            "{}: the game is not installed",
+            game
+        )));
+    }
+    let install_location = library::get_install_location(&app_handle, &library)?;
+    if install_location == local_game.install_location {
+        return Ok(());
     }
-    // advertise a last time to get the 100% of progress
-    download.advertise();
 
-    // download completed
+    // 2 - Move the installed files
+    let source = local_game.get_install_folder(&app_handle)?;
+    let moved_game = Game {
+        install_location: install_location.to_owned(),
+        ..local_game.to_owned()
+    };
+    let destination = moved_game.get_install_folder(&app_handle)?;
     info!(
-        "Download completed in {:.2} seconds",
-        start_time.elapsed().as_secs_f64()
+        "Moving {} from \"{}\" to \"{}\"",
+        local_game.title,
+        source.display(),
+        destination.display()
     );
+    let mut download = GameDownload::new(game, app_handle.clone());
+    let resources_folder = local_game.get_resources_folder(&app_handle)?;
+    library::move_install(
+        &local_game,
+        &source,
+        &destination,
+        &resources_folder,
+        &mut download,
+    )?;
 
-    // 4 - Extract the zip file to the game folder
-    if local_game.game_archive.need_extract {
-        download.set_steps(crate::download::DownloadSteps::Extracting);
-
-        zip_extract::extract(
-            Cursor::new(bytes),
-            &game_data_folder,
-            local_game.game_archive.strip_top_level_folder,
-        )?;
-
-        // 5 - Update the local game list with the downloaded file path
+    // 3 - Update the local game list with the new location of the game
+    let executable_path = moved_game.game_archive.get_executable_path(&destination)?;
+    {
         let mut game_list = LOCAL_GAME_LIST.write().await;
         let update_local_game = game_list.get_mut(&game).ok_or(GameListFetchError(format!(
             "Game with id {} not found",
             game
         )))?;
-        update_local_game.game_archive.link.local_path = Some(
-            game_data_folder
-                .join(PathBuf::from(&local_game.game_archive.path_to_executable).as_os_str()),
+        update_local_game.install_location = install_location;
+        update_local_game.game_archive.link.local_path = Some(executable_path);
+        update_local_game.rollback_revision = None;
+        update_local_game.rollback_version = None;
+    }
+    save_game_list(&app_handle).await?;
+
+    // 4 - Delete the source and the previous revision kept next to it once the game is known to be at its new location.
+    // The move succeeded at this point, a leftover folder is only logged
+    download.set_steps(DownloadSteps::Cleaning);
+    if let Err(e) = library::remove_moved_install(&local_game, &source, &resources_folder) {
+        error!(
+            "Error removing the previous install \"{}\": {:?}",
+            source.display(),
+            e
+        );
+    }
+    if let Err(e) = install::remove_previous_install(&source) {
+        error!(
+            "Error removing the previous revision of \"{}\": {:?}",
+            source.display(),
+            e
         );
+    }
 
-        // 6 - Delete the zip file
-        info!("Cleaning downloaded files");
-        download.set_steps(crate::download::DownloadSteps::Cleaning);
-    } else {
+    download.set_steps(DownloadSteps::Complete);
+    Ok(())
+}
+
+/// ## Import game command
+/// **Description**: Register a game of the catalog as installed from a folder, without downloading it.<br>
+/// The folder must contain the executable of the game. If it was installed by the launcher, its files are also verified
+/// against their hashes. A folder outside of the library folders is copied to the library folder of the game, and left untouched.
+/// The progress is broadcast through the download progress event of the game.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('import_game', {game: id, folder: "D:\\Builds\\MyGame", revision: 12})
+/// .then(() => {
+///   // game imported
+/// })
+/// .catch((error) => {
+///  console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - game (u8)\[FrontEnd]: The id of the catalog game held by the folder.<br>
+/// - folder (PathBuf)\[FrontEnd]: The folder holding the files of the game.<br>
+/// - revision (Option<u64>)\[FrontEnd]: The revision of the files, the latest one of the catalog if omitted.
+///   Ignored for folders installed by the launcher, whose revision is known.
+#[tauri::command]
+pub async fn import_game(
+    app_handle: tauri::AppHandle,
+    game: u8,
+    folder: PathBuf,
+    revision: Option<u64>,
+) -> errors::Result<()> {
+    let _active_download = DOWNLOAD_LOCK.lock().await;
+    if queue::is_queued(game) {
+        return Err(GameImportError(format!(
+            "{}: the game is being downloaded",
+            game
+        )));
+    }
+
+    // 1 - Get the game from the local game list
+    let local_game = {
+        let game_list = LOCAL_GAME_LIST.read().await;
+        game_list
+            .get(&game)
+            .ok_or(GameListFetchError(format!(
+                "Game with id {} not found",
+                game
+            )))?
+            .to_owned()
+    };
+    if local_game.game_archive.link.local_path.is_some() {
+        return Err(GameImportError(format!(
+            "{}: the game is already installed",
+            game
+        )));
+    }
+
+    // 2 - Check the folder holds the game
+    info!(
+        "Importing {} from \"{}\"",
+        local_game.title,
+        folder.display()
+    );
+    let mut download = GameDownload::new(game, app_handle.clone());
+    let install_manifest =
+        import::check_game_folder(&local_game, &folder, revision, &mut download)?;
+
+    // 3 - Keep a folder that is already the install folder of the game in a library, copy the others
+    let mut imported_game = local_game.to_owned();
+    let install_folder = match import::find_library_folder(&app_handle, &local_game, &folder)? {
+        Some(library_folder) => {
+            imported_game.install_location =
+                library::get_install_location(&app_handle, &library_folder)?;
+            folder
+        }
+        None => {
+            let install_folder = local_game.get_install_folder(&app_handle)?;
+            if install_folder.starts_with(&folder) || folder.starts_with(&install_folder) {
+                return Err(GameImportError(format!(
+                    "{}: the folder overlaps the install folder \"{}\"",
+                    game,
+                    install_folder.display()
+                )));
+            }
+            library::copy_install(
+                &local_game,
+                &folder,
+                &install_folder,
+                &local_game.get_resources_folder(&app_handle)?,
+                &mut download,
+            )?;
+            install_folder
+        }
+    };
+    install_manifest.save(&install_folder)?;
+
+    // 4 - Update the local game list with the imported install
+    let executable_path = install::make_game_executable(&imported_game, &install_folder)?;
+    {
         let mut game_list = LOCAL_GAME_LIST.write().await;
         let update_local_game = game_list.get_mut(&game).ok_or(GameListFetchError(format!(
             "Game with id {} not found",
             game
         )))?;
-        update_local_game.game_archive.link.local_path = Some(
-            archive_path
-                .join(PathBuf::from(&local_game.game_archive.path_to_executable).as_os_str()),
-        );
-    }
-
-    // 7 - Update the local game list and give it to the frontend
-    {
-        let store = match app_handle.store(env::STORE_FILE_NAME) {
-            Ok(store) => store,
-            Err(e) => return Err(errors::Verror::StoreAccessError(e.to_string())),
-        };
-        let game_list = LOCAL_GAME_LIST.read().await;
-        store.set(
-            env::STORE_LOCAL_GAME_LIST_KEY,
-            serde_json::to_value(&*game_list).unwrap(),
-        );
+        update_local_game.install_location = imported_game.install_location;
+        update_local_game.set_installed(executable_path, install_manifest.revision);
     }
-    // Use the get game list command to update the frontend ensuring the format is always the same for the frontend
-    app_handle.emit(env::EVENT_GAME_LIST_UPDATED, get_game_list().await?)?;
+    save_game_list(&app_handle).await?;
 
-    download.complete().await;
+    download.set_steps(DownloadSteps::Complete);
     Ok(())
 }
 
-
 /// ## Launch a game
 /// **Description**: Launch a game using its executable file.<br>
 /// **Frontend usage**:
@@ -326,8 +1385,11 @@ pub async fn launch(app_handle: tauri::AppHandle, game: u8) -> errors::Result<()
         }
         Ok(mut child) => {
             info!("Game {} launched", game);
+            RUNNING_GAMES.lock().unwrap().insert(game);
             // wait for the game to finish
-            match child.wait() {
+            let exit_status = child.wait();
+            RUNNING_GAMES.lock().unwrap().remove(&game);
+            match exit_status {
                 Ok(status) => {
                     info!("Game {} terminated with status: {}", game, status);
 
@@ -349,3 +1411,116 @@ pub async fn launch(app_handle: tauri::AppHandle, game: u8) -> errors::Result<()
 
     Ok(())
 }
+
+///## Get settings command
+/// **Description**: Get the launcher settings editable by the player.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('get_settings')
+/// .then((settingsData) => {
+///    let settings = JSON.parse(settingsData);
+///    // use the settings ...
+/// })
+/// .catch((error) => {
+///   console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.
+///
+/// **Returns**:
+/// - String : The settings in JSON format. e.g. "{"http": {"proxy": null, ...}}"
+#[tauri::command]
+pub fn get_settings(app_handle: tauri::AppHandle) -> Result<String, Verror> {
+    Ok(serde_json::to_string(&settings::load_settings(
+        &app_handle,
+    ))?)
+}
+
+///## Set settings command
+/// **Description**: Save the launcher settings and apply them.
+/// The shared HTTP client is rebuilt so the new proxy, timeouts, user agent and certificate are used by the next requests,
+/// the archive cache is trimmed to its new size limit, and the frontend is allowed to access the new library folders.<br>
+/// A library folder can't be removed while a game is installed in it.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('set_settings', {settings: {http: {proxy: "http://proxy.local:3128", ...}}})
+/// .then(() => {
+///   // settings saved
+/// })
+/// .catch((error) => {
+///   console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - http_client (State<HttpClient>)\[tauri-Backend]: The shared HTTP client to rebuild.<br>
+/// - settings (Settings)\[FrontEnd]: The new settings. Missing fields take their default value.
+#[tauri::command]
+pub async fn set_settings(
+    app_handle: tauri::AppHandle,
+    http_client: State<'_, HttpClient>,
+    settings: Settings,
+) -> errors::Result<()> {
+    // Rebuild the client first so invalid settings are rejected before being saved
+    http_client.rebuild(&settings.http)?;
+    library::update_library_folders(&app_handle, &settings.library_folders).await?;
+    settings::save_settings(&app_handle, &settings)?;
+    cache::apply_settings(&app_handle, &settings.archive_cache);
+
+    // The games left in a removed library folder are moved back to the default one
+    save_game_list(&app_handle).await
+}
+
+///## Set credentials command
+/// **Description**: Save the credentials of a realm, used to download the private and early-access builds of this realm.<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('set_credentials', {realm: "closed_beta", credentials: {access_token: "...", refresh_token: "...", expires_at: 1735689600, token_endpoint: "https://..."}})
+/// .then(() => {
+///   // credentials saved
+/// })
+/// .catch((error) => {
+///   console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - realm (String)\[FrontEnd]: The realm of the credentials, as declared in the `auth` of the catalog links.<br>
+/// - credentials (Credentials)\[FrontEnd]: The access token and the optional refresh information.
+#[tauri::command]
+pub fn set_credentials(
+    app_handle: tauri::AppHandle,
+    realm: String,
+    credentials: Credentials,
+) -> errors::Result<()> {
+    auth::set_credentials(&app_handle, &realm, credentials)
+}
+
+///## Remove credentials command
+/// **Description**: Forget the credentials of a realm (e.g. when the player logs out).<br>
+/// **Frontend usage**:
+/// ```typescript
+/// invoke('remove_credentials', {realm: "closed_beta"})
+/// .then(() => {
+///   // credentials removed
+/// })
+/// .catch((error) => {
+///   console.error(error);
+/// });
+/// ```
+///
+/// **Parameters**:<br>
+/// NAME (TYPE)\[SOURCE]: DESCRIPTION
+/// - app_handle (AppHandle)\[tauri-Backend]: The handle to the application used to access the store.<br>
+/// - realm (String)\[FrontEnd]: The realm of the credentials to remove.
+#[tauri::command]
+pub fn remove_credentials(app_handle: tauri::AppHandle, realm: String) -> errors::Result<()> {
+    auth::remove_credentials(&app_handle, &realm)
+}
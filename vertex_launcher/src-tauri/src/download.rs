@@ -1,10 +1,20 @@
-use crate::env;
-use crate::env::{generate_download_complete_message, LOCAL_GAME_LIST};
-use crate::errors::Verror::GameListFetchError;
+use crate::env::{generate_download_complete_message, LOCAL_GAME_LIST, UPDATE_RATE};
+use crate::errors::Verror::{GameListFetchError, GameResourceDownloadError, MessageError};
+use crate::{auth, env, errors};
+use futures_util::future::try_join_all;
+use futures_util::stream::StreamExt;
 use log::error;
 use serde::Serialize;
 use serde_json::Value;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
+use tauri_plugin_http::reqwest::header::{CONTENT_RANGE, RANGE};
+use tauri_plugin_http::reqwest::{RequestBuilder, Response, StatusCode};
+use tokio::fs::OpenOptions;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, Serialize)]
@@ -15,6 +25,12 @@ pub enum DownloadSteps {
     Downloading,
     // The download is extracting the downloaded zip file
     Extracting,
+    // The download is applying delta patches on the installed files
+    Patching,
+    // The installed files are copied to another library folder
+    Moving,
+    // The installed files are checked against their expected hashes
+    Verifying,
     // The download is cleaning up the extracted files
     Cleaning,
     // The download is complete
@@ -33,6 +49,10 @@ pub struct GameDownload {
     pub time_start: Option<std::time::Instant>,
     // The steps of the download
     pub steps: DownloadSteps,
+    // The number of archive entries extracted so far
+    pub processed_entries: u64,
+    // The number of entries in the archive, None if the archive format doesn't list them upfront
+    pub total_entries: Option<u64>,
     // The event to broadcast when the download progress changes
     pub event_name: String,
 
@@ -47,6 +67,8 @@ impl GameDownload {
             file_size: 0,
             downloaded: 0,
             steps: DownloadSteps::Starting,
+            processed_entries: 0,
+            total_entries: None,
             // The event name result of the concatenation of the download progress event and the game id
             event_name: format!("{}_{}", env::EVENT_DOWNLOAD_PROGRESS, game_id),
             app_handle,
@@ -74,6 +96,11 @@ impl GameDownload {
         }
     }
 
+    pub fn update_entries(&mut self, processed_entries: u64, total_entries: Option<u64>) {
+        self.processed_entries = processed_entries;
+        self.total_entries = total_entries;
+    }
+
     pub fn advertise(&self) {
         // broadcast the download progress
         self.app_handle
@@ -123,10 +150,15 @@ impl GameDownload {
             // Calculate the remaining time of the download rounded to 2 decimal places
             "remaining_time": self.get_formated_remaining_time(),
             "steps": self.steps,
+            "processed_entries": self.processed_entries,
+            "total_entries": self.total_entries,
         })
     }
 
     fn get_percentage(&self) -> f64 {
+        if self.file_size == 0 {
+            return 0.0;
+        }
         (self.downloaded as f64 / self.file_size as f64) * 100.0
     }
 
@@ -155,7 +187,7 @@ impl GameDownload {
     }
 
     fn get_remaining_time(&self) -> f64 {
-        (self.file_size - self.downloaded) as f64 / self.get_speed_b()
+        self.file_size.saturating_sub(self.downloaded) as f64 / self.get_speed_b()
     }
 
     pub fn get_formated_remaining_time(&self) -> String {
@@ -174,3 +206,215 @@ impl GameDownload {
         }
     }
 }
+
+/// Download the resource requested by `request` into the file at `file_path`.<br>
+/// If the server supports byte ranges requests and the resource is bigger than `env::DOWNLOAD_SEGMENT_MIN_SIZE`,
+/// the file is split into `env::DOWNLOAD_SEGMENT_COUNT` segments fetched over parallel connections
+/// and written at their own offset. Otherwise, the resource is downloaded in a single stream.<br>
+/// The progress of every connection is combined and reported through `download`.
+pub async fn download_file(
+    request: RequestBuilder,
+    file_path: &Path,
+    download: &mut GameDownload,
+) -> errors::Result<()> {
+    match probe_ranges(&request).await? {
+        RangeProbe::Ranged(Some(total_size)) if total_size >= env::DOWNLOAD_SEGMENT_MIN_SIZE => {
+            download_segmented(&request, file_path, total_size, download).await
+        }
+        RangeProbe::Ranged(_) => {
+            download_single_stream(request.send().await?, file_path, download).await
+        }
+        // The whole resource is already being served, there is no need to request it again
+        RangeProbe::Whole(response) => download_single_stream(response, file_path, download).await,
+    }
+}
+
+/// The answer of the server to a request for the first byte of a resource.
+enum RangeProbe {
+    /// The server supports byte ranges requests. Holds the total size of the resource, if given.
+    Ranged(Option<u64>),
+    /// The server ignored the range and answers with the whole resource.
+    Whole(Response),
+}
+
+/// Ask the server for the first byte of the resource to know if it supports byte ranges requests.
+async fn probe_ranges(request: &RequestBuilder) -> errors::Result<RangeProbe> {
+    let response = clone_request(request)?
+        .header(RANGE, "bytes=0-0")
+        .send()
+        .await?;
+
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return Ok(RangeProbe::Whole(response));
+    }
+
+    // The Content-Range header has the form "bytes 0-0/<total size>"
+    Ok(RangeProbe::Ranged(
+        response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit('/').next())
+            .and_then(|total_size| total_size.parse::<u64>().ok()),
+    ))
+}
+
+/// Download the whole resource from the response of a single connection.
+async fn download_single_stream(
+    response: Response,
+    file_path: &Path,
+    download: &mut GameDownload,
+) -> errors::Result<()> {
+    auth::check_response(&response)?;
+
+    let total_size = response
+        .content_length()
+        .ok_or(MessageError("Failed to get the content length".to_string()))?;
+
+    download.set_file_size(total_size);
+    download.set_steps(DownloadSteps::Downloading);
+
+    let mut file = tokio::fs::File::create(file_path).await?;
+    let mut last_update = Instant::now() - Duration::from_millis(UPDATE_RATE as u64);
+    let mut downloaded: u64 = 0;
+    let mut stream = response.bytes_stream();
+
+    while let Some(stream_item) = stream.next().await {
+        let chunk = stream_item.map_err(|e| {
+            MessageError(format!(
+                "Failed to get a chunk for game {}: {:?}",
+                download.game_id, e
+            ))
+        })?;
+
+        file.write_all(&chunk).await?;
+        downloaded = std::cmp::min(downloaded + (chunk.len() as u64), total_size);
+        download.update(downloaded, None);
+
+        if (last_update.elapsed().as_millis() as u16) < UPDATE_RATE {
+            // don't advertise the download progress too often
+            continue;
+        }
+        download.advertise();
+        last_update = Instant::now();
+    }
+    file.flush().await?;
+
+    // advertise a last time to get the 100% of progress
+    download.advertise();
+    Ok(())
+}
+
+/// Download the resource through `env::DOWNLOAD_SEGMENT_COUNT` parallel connections.<br>
+/// Each segment reports the bytes it wrote to a shared counter, which is advertised every `UPDATE_RATE` ms.
+async fn download_segmented(
+    request: &RequestBuilder,
+    file_path: &Path,
+    total_size: u64,
+    download: &mut GameDownload,
+) -> errors::Result<()> {
+    download.set_file_size(total_size);
+    download.set_steps(DownloadSteps::Downloading);
+
+    // Allocate the whole file first so every segment can write at its own offset
+    tokio::fs::File::create(file_path)
+        .await?
+        .set_len(total_size)
+        .await?;
+
+    let downloaded = AtomicU64::new(0);
+    let downloaded_ref = &downloaded;
+    let segment_size = total_size.div_ceil(env::DOWNLOAD_SEGMENT_COUNT);
+    let segments = (0..env::DOWNLOAD_SEGMENT_COUNT)
+        .map(|index| index * segment_size)
+        .filter(|start| *start < total_size)
+        .map(|start| {
+            let end = std::cmp::min(start + segment_size, total_size) - 1;
+            Ok(download_segment(
+                clone_request(request)?,
+                file_path,
+                start,
+                end,
+                downloaded_ref,
+            ))
+        })
+        .collect::<errors::Result<Vec<_>>>()?;
+
+    let segments = try_join_all(segments);
+    tokio::pin!(segments);
+    let mut ticker = tokio::time::interval(Duration::from_millis(UPDATE_RATE as u64));
+    loop {
+        tokio::select! {
+            result = &mut segments => {
+                result?;
+                break;
+            }
+            _ = ticker.tick() => {
+                download.update(downloaded.load(Ordering::Relaxed), None);
+                download.advertise();
+            }
+        }
+    }
+
+    // advertise a last time to get the 100% of progress
+    download.update(downloaded.load(Ordering::Relaxed), None);
+    download.advertise();
+    Ok(())
+}
+
+/// Download the bytes `start..=end` of the resource and write them at the same offset in the file.
+async fn download_segment(
+    request: RequestBuilder,
+    file_path: &Path,
+    start: u64,
+    end: u64,
+    downloaded: &AtomicU64,
+) -> errors::Result<()> {
+    let response = request
+        .header(RANGE, format!("bytes={}-{}", start, end))
+        .send()
+        .await?;
+    auth::check_response(&response)?;
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return Err(GameResourceDownloadError(format!(
+            "The range {}-{} was not served as partial content: {:?}",
+            start, end, response
+        )));
+    }
+
+    let mut file = OpenOptions::new().write(true).open(file_path).await?;
+    file.seek(SeekFrom::Start(start)).await?;
+
+    // Never write past the segment, a server sending more bytes would overwrite the next one
+    let segment_size = end - start + 1;
+    let mut written: u64 = 0;
+    let mut stream = response.bytes_stream();
+    while let Some(stream_item) = stream.next().await {
+        let chunk = stream_item?;
+        if written + chunk.len() as u64 > segment_size {
+            return Err(GameResourceDownloadError(format!(
+                "The range {}-{} received more than {} bytes",
+                start, end, segment_size
+            )));
+        }
+        file.write_all(&chunk).await?;
+        written += chunk.len() as u64;
+        downloaded.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+    }
+    file.flush().await?;
+
+    if written != segment_size {
+        return Err(GameResourceDownloadError(format!(
+            "The range {}-{} is incomplete: received {} bytes",
+            start, end, written
+        )));
+    }
+    Ok(())
+}
+
+/// Clone a request so it can be sent several times.
+fn clone_request(request: &RequestBuilder) -> errors::Result<RequestBuilder> {
+    request.try_clone().ok_or(MessageError(
+        "Failed to clone the download request".to_string(),
+    ))
+}
@@ -36,6 +36,15 @@ pub(crate) const STORE_REMOTE_GAME_LIST_KEY: &str = "remote_games";
 /// The key used to store the local game list with their.
 pub(crate) const STORE_LOCAL_GAME_LIST_KEY: &str = "local_games";
 
+/// The key used to store the launcher settings editable by the player.
+pub(crate) const STORE_SETTINGS_KEY: &str = "settings";
+
+/// The key used to store the credentials of each realm used by authenticated links.
+pub(crate) const STORE_CREDENTIALS_KEY: &str = "credentials";
+
+/// The key used to store the ids of the active and queued downloads, to resume them on the next startup.
+pub(crate) const STORE_DOWNLOAD_QUEUE_KEY: &str = "download_queue";
+
 /// List of games stored in the local store in the STORE_LOCAL_GAME_LIST_KEY.<br>
 /// You change the default capacity of the HashMap if you have more games to store.
 lazy_static! {
@@ -43,6 +52,12 @@ lazy_static! {
         Arc::new(RwLock::new(HashMap::<u8, Game>::with_capacity(3)));
 }
 
+/// Ids of the games currently running, launched by the launch command.
+lazy_static! {
+    pub(crate) static ref RUNNING_GAMES: Arc<std::sync::Mutex<std::collections::HashSet<u8>>> =
+        Arc::new(std::sync::Mutex::new(std::collections::HashSet::new()));
+}
+
 ///# ====================================
 ///# == Logging configuration
 ///# ====================================
@@ -105,6 +120,26 @@ lazy_static! {
 pub(crate) const LOG_TIMEZONE: tauri_plugin_log::TimezoneStrategy =
     tauri_plugin_log::TimezoneStrategy::UseLocal;
 
+///# ====================================
+///# == HTTP client configuration
+///# ====================================
+/// Default values of the HTTP settings. The player can override them in the launcher settings.
+
+/// Maximum time (in seconds) to establish a connection with a server.
+pub(crate) const HTTP_DEFAULT_CONNECT_TIMEOUT: u64 = 10;
+
+/// Maximum time (in seconds) without receiving any data before a request is aborted.<br>
+/// There is no total timeout, as downloading a game can take hours.
+pub(crate) const HTTP_DEFAULT_READ_TIMEOUT: u64 = 30;
+
+/// User agent sent with every request.
+pub fn generate_default_user_agent() -> String {
+    format!("VertexLauncher/{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Access tokens expiring in less than this delay (in seconds) are refreshed before being used.
+pub(crate) const CREDENTIALS_REFRESH_MARGIN: u64 = 60;
+
 ///# ====================================
 ///# == Game download configuration
 ///# ====================================
@@ -113,11 +148,52 @@ pub(crate) const LOG_TIMEZONE: tauri_plugin_log::TimezoneStrategy =
 /// the download progression.
 pub(crate) const UPDATE_RATE: u16 = 100; // default: 100ms
 
+/// The maximum number of games whose resources (images, icons) are downloaded at the same time on startup.
+pub(crate) const RESOURCE_DOWNLOAD_CONCURRENCY: usize = 6;
+
+/// The number of parallel connections used to download an archive when the server
+/// supports byte ranges requests.
+pub(crate) const DOWNLOAD_SEGMENT_COUNT: u64 = 4;
+
+/// The minimum size of an archive (in bytes) before it gets split into segments.<br>
+/// Smaller archives are downloaded in a single stream as the extra connections are not worth it.
+pub(crate) const DOWNLOAD_SEGMENT_MIN_SIZE: u64 = (1024 * 1024) * 32; // 32MB
+
+/// The folder of the app data directory where the content manifest of each install is kept.
+pub(crate) const CONTENT_MANIFESTS_FOLDER: &str = "manifests";
+
+/// The file of each install folder listing the files written by the launcher for the game.
+pub(crate) const INSTALL_MANIFEST_FILE: &str = ".vertex_install.json";
+
+/// The folder of the app data directory where the archives are kept when the archive cache is enabled.
+pub(crate) const ARCHIVE_CACHE_FOLDER: &str = "archives";
+
+/// The default size limit (in bytes) of the archive cache.
+pub(crate) const ARCHIVE_CACHE_DEFAULT_MAX_SIZE: u64 = (1024 * 1024 * 1024) * 20; // 20GB
+
+/// Ids of the games being downloaded or waiting to be, in the order they were requested.<br>
+/// A std Mutex is used as the queue is never locked across an await point.
+lazy_static! {
+    pub(crate) static ref DOWNLOAD_QUEUE: Arc<std::sync::Mutex<Vec<u8>>> =
+        Arc::new(std::sync::Mutex::new(Vec::new()));
+}
+
+/// Lock held by the active download, so queued downloads run one after another.
+lazy_static! {
+    pub(crate) static ref DOWNLOAD_LOCK: Arc<tokio::sync::Mutex<()>> =
+        Arc::new(tokio::sync::Mutex::new(()));
+}
+
 /// Notification message when the download is complete
 pub fn generate_download_complete_message(game_name: &str) -> String {
     format!("{} has been successfully downloaded.", game_name)
 }
 
+/// Notification message when an update is available for a game whose update policy is to notify
+pub fn generate_update_available_message(game_name: &str, version: &str) -> String {
+    format!("{} {} is available.", game_name, version)
+}
+
 ///# ====================================
 ///# == Event configuration
 ///# ====================================
@@ -129,11 +205,20 @@ pub(crate) const EVENT_INIT: &str = "app_initialized";
 /// The event name used to broadcast the game download progress
 pub(crate) const EVENT_DOWNLOAD_PROGRESS: &str = "download_progress";
 
+/// The event name used to share the download queue with the frontend each time it changes
+pub(crate) const EVENT_DOWNLOAD_QUEUE_UPDATED: &str = "download_queue_updated";
+
 /// The event name used to broadcast the game download completed
 pub(crate) const EVENT_DOWNLOAD_COMPLETED: &str = "download_completed";
 
+/// The event name used to share with the frontend the updates found on startup for the games whose update policy is to notify
+pub(crate) const EVENT_UPDATES_AVAILABLE: &str = "updates_available";
+
 /// The event name used to share the new game list with the frontend
 pub(crate) const EVENT_GAME_LIST_UPDATED: &str = "game_list_updated";
 
+/// The event name used to broadcast that the resources (images, icons) of a game have been downloaded on startup
+pub(crate) const EVENT_GAME_RESOURCES_PROGRESS: &str = "game_resources_progress";
+
 /// The event name used to broadcast the game process is terminated
 pub(crate) const EVENT_GAME_PROCESS_TERMINATED: &str = "game_process_terminated";
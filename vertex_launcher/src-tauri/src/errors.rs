@@ -7,7 +7,7 @@ pub enum Verror {
     Io(#[from] std::io::Error),
 
     #[error(transparent)]
-    ZipError(#[from] zip_extract::ZipExtractError),
+    ZipArchiveError(#[from] zip::result::ZipError),
 
     #[error(transparent)]
     TauriError(#[from] tauri::Error),
@@ -34,17 +34,76 @@ pub enum Verror {
     #[error("An error occurred while extracting resources for the game {0}")]
     GameResourceExtractionError(String),
 
+    #[error("The path {0} leads outside of the game folder.")]
+    UnsafePathError(String),
+
+    #[error("The format of the archive {0} is not supported.")]
+    UnsupportedArchiveFormat(String),
+
+    #[error("An error occurred while patching the game {0}")]
+    GamePatchError(String),
+
+    #[error("The file {0} does not match its expected hash.")]
+    GameResourceIntegrityError(String),
+
+    #[error(
+        "Not enough disk space to install the game: {} MB missing ({} MB required, {} MB available).",
+        to_mb(.required - .available),
+        to_mb(*.required),
+        to_mb(*.available)
+    )]
+    NotEnoughDiskSpace { required: u64, available: u64 },
+
+    #[error("An error occurred while uninstalling the game {0}")]
+    GameUninstallError(String),
+
+    #[error("An error occurred while verifying the game {0}")]
+    GameVerificationError(String),
+
+    #[error("An error occurred while repairing the game {0}")]
+    GameRepairError(String),
+
+    #[error("The library folder {0} can't be used.")]
+    LibraryFolderError(String),
+
+    #[error("An error occurred while moving the game {0}")]
+    GameMoveError(String),
+
+    #[error("An error occurred while importing the game {0}")]
+    GameImportError(String),
+
+    #[error("An error occurred while updating the game {0}")]
+    GameUpdateError(String),
+
+    #[error("An error occurred while rolling back the game {0}")]
+    GameRollbackError(String),
+
     #[error("An error occurred while launching the game {0}")]
     GameLaunchError(String),
 
     #[error("An error occurred while fetching the game {0} from the local list.")]
     GameListFetchError(String),
 
+    // AUTHENTICATION ERRORS
+    #[error("No credentials found for {0}. Please log in to access this content.")]
+    CredentialsMissing(String),
+
+    #[error("The credentials for {0} have expired. Please log in again.")]
+    CredentialsExpired(String),
+
+    #[error("The credentials were rejected by {0}.")]
+    CredentialsRejected(String),
+
     // STORE ERRORS
     #[error("An error occurred while fetching the store at {0}")]
     StoreAccessError(String),
 }
 
+/// Convert a size in bytes to megabytes, rounded up so a missing byte is never displayed as 0 MB.
+fn to_mb(bytes: u64) -> u64 {
+    bytes.div_ceil(1024 * 1024)
+}
+
 // we must manually implement serde::Serialize
 impl serde::Serialize for Verror {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
@@ -1,15 +1,20 @@
-use crate::errors;
-use crate::errors::Verror::{GameResourceDownloadError, Io, MessageError};
+use crate::archive::ArchiveFormat;
+use crate::auth::LinkAuth;
+use crate::env::LOCAL_GAME_LIST;
+use crate::errors::Verror::{
+    GameListFetchError, GameResourceDownloadError, Io, MessageError, UnsupportedArchiveFormat,
+};
 use crate::games::LinkType::{BackgroundImage, GameArchiveLink, NavigationIcon};
+use crate::settings::UpdatePolicy;
+use crate::{auth, errors, paths};
 use log::error;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use std::fs;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::{AppHandle, Manager};
-use tauri_plugin_http::reqwest;
 
 /// A struct that represents a link to a resource. It will contain all the information needed to
 /// download the resource and save it to the app's data directory.<br>
@@ -20,15 +25,25 @@ pub struct Link {
     pub name: String,
     pub revision: u64,
     pub local_path: Option<PathBuf>,
+    /// The credentials required to fetch the url, None for public links.
+    #[serde(default)]
+    pub auth: Option<LinkAuth>,
 }
 
 impl Link {
-    pub fn new(url: String, name: String, revision: u64, local_path: Option<PathBuf>) -> Link {
+    pub fn new(
+        url: String,
+        name: String,
+        revision: u64,
+        local_path: Option<PathBuf>,
+        auth: Option<LinkAuth>,
+    ) -> Link {
         Link {
             url,
             name,
             revision,
             local_path,
+            auth,
         }
     }
 
@@ -39,8 +54,12 @@ impl Link {
         let local_path: Option<PathBuf> = json_map
             .get("local_path")
             .map(|value| PathBuf::deserialize(value).unwrap());
+        let auth: Option<LinkAuth> = match json_map.get("auth") {
+            Some(value) if !value.is_null() => Some(LinkAuth::deserialize(value)?),
+            _ => None,
+        };
 
-        Ok(Link::new(url, name, revision, local_path))
+        Ok(Link::new(url, name, revision, local_path, auth))
     }
 
     fn is_json_valid(json: &Value) -> bool {
@@ -55,7 +74,102 @@ impl Link {
             Some(value) => value.is_string(),
             None => true,
         };
-        base_validity && local_path_validity
+        // check if the authentication scheme is known when the link requires credentials
+        let auth_validity = match json.get("auth") {
+            Some(value) => value.is_null() || LinkAuth::deserialize(value).is_ok(),
+            None => true,
+        };
+        base_validity && local_path_validity && auth_validity
+    }
+}
+
+/// A binary delta published by the catalog to update an install from `from_revision`
+/// to the revision of its `link`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivePatch {
+    pub from_revision: u64,
+    pub link: Link,
+    /// The size (in bytes) of the patch archive, as declared by the catalog.
+    #[serde(default)]
+    pub download_size: Option<u64>,
+}
+
+impl ArchivePatch {
+    pub fn from_json_object(json_map: &Map<String, Value>) -> errors::Result<ArchivePatch> {
+        let from_revision = json_map["from_revision"].as_u64().unwrap();
+        let link = Link::from_json_object(json_map["link"].as_object().unwrap())?;
+
+        Ok(ArchivePatch {
+            from_revision,
+            link,
+            download_size: json_map.get("download_size").and_then(|v| v.as_u64()),
+        })
+    }
+
+    fn is_json_valid(json: &Value) -> bool {
+        json.get("from_revision").is_some()
+            && json["from_revision"].is_u64()
+            && json.get("link").is_some()
+            && json["link"].is_object()
+            && Link::is_json_valid(&json["link"])
+            && (json["download_size"] == Value::Null || json["download_size"].is_u64())
+    }
+}
+
+/// A past version of a game published by the catalog, with the archive of its build.<br>
+/// The fields it omits are the same as the ones of the latest archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveVersion {
+    pub version: String,
+    pub link: Link,
+    /// The format of the archive. None to guess it from the extension of the link name.
+    #[serde(default)]
+    pub archive_format: Option<ArchiveFormat>,
+    /// The relative path to the executable, None if it is the same as in the latest archive.
+    #[serde(default)]
+    pub path_to_executable: Option<String>,
+    /// The size (in bytes) of the archive to download, as declared by the catalog.
+    #[serde(default)]
+    pub download_size: Option<u64>,
+    /// The size (in bytes) of the version once installed, as declared by the catalog.
+    #[serde(default)]
+    pub installed_size: Option<u64>,
+}
+
+impl ArchiveVersion {
+    pub fn from_json_object(json_map: &Map<String, Value>) -> errors::Result<ArchiveVersion> {
+        let version = json_map["version"].as_str().unwrap().to_string();
+        let link = Link::from_json_object(json_map["link"].as_object().unwrap())?;
+        let archive_format = match json_map.get("archive_format") {
+            Some(Value::Null) | None => None,
+            Some(value) => Some(serde_json::from_value(value.to_owned())?),
+        };
+
+        Ok(ArchiveVersion {
+            version,
+            link,
+            archive_format,
+            path_to_executable: json_map
+                .get("path_to_executable")
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_string()),
+            download_size: json_map.get("download_size").and_then(|v| v.as_u64()),
+            installed_size: json_map.get("installed_size").and_then(|v| v.as_u64()),
+        })
+    }
+
+    fn is_json_valid(json: &Value) -> bool {
+        json.get("version").is_some()
+            && json["version"].is_string()
+            && json.get("link").is_some()
+            && json["link"].is_object()
+            && Link::is_json_valid(&json["link"])
+            && (json["archive_format"] == Value::Null
+                || serde_json::from_value::<ArchiveFormat>(json["archive_format"].to_owned())
+                    .is_ok())
+            && (json["path_to_executable"] == Value::Null || json["path_to_executable"].is_string())
+            && (json["download_size"] == Value::Null || json["download_size"].is_u64())
+            && (json["installed_size"] == Value::Null || json["installed_size"].is_u64())
     }
 }
 
@@ -63,9 +177,34 @@ impl Link {
 pub struct GameArchive {
     pub link: Link,
     pub need_extract: bool,
+    /// The format of the archive. None to guess it from the extension of the link name.
+    #[serde(default)]
+    pub archive_format: Option<ArchiveFormat>,
     pub strip_top_level_folder: bool,
     pub path_to_executable: String,
+    /// Relative paths of the other binaries started by the game (e.g. a crash reporter), made executable at install.
+    #[serde(default)]
+    pub helper_executables: Vec<String>,
     pub need_update: bool,
+    /// The revision of the files currently installed, None if the game is not installed.
+    #[serde(default)]
+    pub installed_revision: Option<u64>,
+    /// The deltas published by the catalog between revisions of this archive.
+    #[serde(default)]
+    pub patches: Vec<ArchivePatch>,
+    /// The url of a content manifest listing every file of the latest revision.<br>
+    /// When provided, only the files that changed are downloaded instead of the whole archive.
+    #[serde(default)]
+    pub manifest_url: Option<String>,
+    /// The size (in bytes) of the archive to download, as declared by the catalog.
+    #[serde(default)]
+    pub download_size: Option<u64>,
+    /// The size (in bytes) of the game once installed, as declared by the catalog.
+    #[serde(default)]
+    pub installed_size: Option<u64>,
+    /// The past versions of the game that can still be installed.
+    #[serde(default)]
+    pub versions: Vec<ArchiveVersion>,
 }
 
 impl GameArchive {
@@ -74,13 +213,23 @@ impl GameArchive {
         need_extract: bool,
         strip_top_level_folder: bool,
         path_to_executable: String,
+        patches: Vec<ArchivePatch>,
+        manifest_url: Option<String>,
     ) -> GameArchive {
         GameArchive {
             link,
             need_extract,
+            archive_format: None,
             strip_top_level_folder,
             path_to_executable,
+            helper_executables: Vec::new(),
             need_update: false,
+            installed_revision: None,
+            patches,
+            manifest_url,
+            download_size: None,
+            installed_size: None,
+            versions: Vec::new(),
         }
     }
 
@@ -89,16 +238,67 @@ impl GameArchive {
         let need_extract = json_map["need_extract"].as_bool().unwrap();
         let strip_top_level_folder = json_map["strip_top_level_folder"].as_bool().unwrap();
         let path_to_executable = json_map["path_to_executable"].as_str().unwrap().to_string();
+        let patches = match json_map.get("patches").and_then(|value| value.as_array()) {
+            Some(patches) => patches
+                .iter()
+                .map(|patch| ArchivePatch::from_json_object(patch.as_object().unwrap()))
+                .collect::<errors::Result<Vec<ArchivePatch>>>()?,
+            None => Vec::new(),
+        };
+        let manifest_url = json_map
+            .get("manifest_url")
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string());
 
-        Ok(GameArchive::new(
+        let mut game_archive = GameArchive::new(
             url,
             need_extract,
             strip_top_level_folder,
             path_to_executable,
-        ))
+            patches,
+            manifest_url,
+        );
+        game_archive.download_size = json_map.get("download_size").and_then(|v| v.as_u64());
+        game_archive.installed_size = json_map.get("installed_size").and_then(|v| v.as_u64());
+        game_archive.helper_executables = json_map
+            .get("helper_executables")
+            .and_then(|value| value.as_array())
+            .map(|paths| {
+                paths
+                    .iter()
+                    .filter_map(|path| path.as_str().map(|path| path.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        game_archive.versions = match json_map.get("versions").and_then(|value| value.as_array()) {
+            Some(versions) => versions
+                .iter()
+                .map(|version| ArchiveVersion::from_json_object(version.as_object().unwrap()))
+                .collect::<errors::Result<Vec<ArchiveVersion>>>()?,
+            None => Vec::new(),
+        };
+        game_archive.archive_format = match json_map.get("archive_format") {
+            Some(Value::Null) | None => None,
+            Some(value) => Some(serde_json::from_value(value.to_owned())?),
+        };
+
+        Ok(game_archive)
     }
 
     fn is_json_valid(json: &Value) -> bool {
+        // patches are optional, but each of them must be valid when provided
+        let patches_validity = match json.get("patches") {
+            Some(Value::Array(patches)) => patches.iter().all(ArchivePatch::is_json_valid),
+            Some(value) => value.is_null(),
+            None => true,
+        };
+        // past versions are optional too
+        let versions_validity = match json.get("versions") {
+            Some(Value::Array(versions)) => versions.iter().all(ArchiveVersion::is_json_valid),
+            Some(value) => value.is_null(),
+            None => true,
+        };
+
         json.get("link").is_some()
             && json["link"].is_object()
             && Link::is_json_valid(&json["link"])
@@ -108,16 +308,162 @@ impl GameArchive {
             && json["strip_top_level_folder"].is_boolean()
             && json.get("path_to_executable").is_some()
             && json["path_to_executable"].is_string()
+            && patches_validity
+            && versions_validity
+            && (json["manifest_url"] == Value::Null || json["manifest_url"].is_string())
+            && (json["download_size"] == Value::Null || json["download_size"].is_u64())
+            && (json["installed_size"] == Value::Null || json["installed_size"].is_u64())
+            && (json["helper_executables"] == Value::Null
+                || json["helper_executables"]
+                    .as_array()
+                    .is_some_and(|paths| paths.iter().all(|path| path.is_string())))
+            && (json["archive_format"] == Value::Null
+                || serde_json::from_value::<ArchiveFormat>(json["archive_format"].to_owned())
+                    .is_ok())
+    }
+
+    /// Return the path of the game executable in the install folder.<br>
+    /// Extracted archives and manifest installs contain the executable at `path_to_executable`.
+    /// Otherwise, the downloaded file is the executable itself.
+    pub fn get_executable_path(&self, install_folder: &Path) -> errors::Result<PathBuf> {
+        if self.need_extract || self.manifest_url.is_some() {
+            paths::join_inside(install_folder, &self.path_to_executable)
+        } else {
+            paths::join_inside(install_folder, &self.link.name)
+        }
+    }
+
+    /// Return the format of the archive, declared by the catalog or guessed from the link name.
+    pub fn get_archive_format(&self) -> errors::Result<ArchiveFormat> {
+        self.archive_format
+            .or_else(|| ArchiveFormat::from_file_name(&self.link.name))
+            .ok_or(UnsupportedArchiveFormat(self.link.name.to_owned()))
+    }
+
+    /// Return the disk space (in bytes) needed to install the latest revision, based on the sizes declared by the catalog.<br>
+    /// Archives are extracted next to the current install before replacing it, so full updates need as much room as
+    /// new installs. Updates made in place only need room for their patches, and for a copy of the installed revision
+    /// if `keep_previous` is true.
+    /// Return None if a size needed for the estimate is not declared.
+    pub fn get_required_space(&self, keep_previous: bool) -> Option<u64> {
+        let is_installed = self.installed_revision.is_some();
+        let previous_copy_size = if keep_previous {
+            self.installed_size
+        } else {
+            Some(0)
+        };
+        if self.manifest_url.is_some() {
+            // Files are rebuilt one by one, only a new install needs room for all of them
+            return if is_installed {
+                previous_copy_size
+            } else {
+                self.installed_size
+            };
+        }
+        if let Some(patches) = self.get_patch_chain() {
+            let patches_size: u64 = patches
+                .iter()
+                .map(|patch| patch.download_size)
+                .sum::<Option<u64>>()?;
+            return Some(patches_size + previous_copy_size?);
+        }
+
+        // The installed revision is moved aside, not copied, when it is kept
+        let mut required_space = self.download_size?;
+        if self.need_extract {
+            required_space += self.installed_size?;
+        }
+        Some(required_space)
+    }
+
+    /// Return the deltas to apply, in order, to bring the installed revision to the latest one.<br>
+    /// Return None if the game is not installed, is already up to date, or if no complete
+    /// chain of deltas exists from the installed revision.
+    pub fn get_patch_chain(&self) -> Option<Vec<ArchivePatch>> {
+        let mut revision = self.installed_revision?;
+        if revision >= self.link.revision {
+            return None;
+        }
+
+        let mut chain = Vec::new();
+        while revision < self.link.revision {
+            // When several deltas start from the same revision, take the one that goes the furthest
+            let patch = self
+                .patches
+                .iter()
+                .filter(|patch| {
+                    patch.from_revision == revision
+                        && patch.link.revision > revision
+                        && patch.link.revision <= self.link.revision
+                })
+                .max_by_key(|patch| patch.link.revision)?;
+
+            revision = patch.link.revision;
+            chain.push(patch.clone());
+        }
+
+        Some(chain)
+    }
+
+    /// Return the size (in bytes) to download to bring the install to the latest revision, None if unknown.<br>
+    /// Manifest installs only download the files that changed, which is only known once the manifest is fetched.
+    pub fn get_update_size(&self) -> Option<u64> {
+        if self.manifest_url.is_some() {
+            return None;
+        }
+        match self.get_patch_chain() {
+            Some(patches) => patches.iter().map(|patch| patch.download_size).sum(),
+            None => self.download_size,
+        }
+    }
+
+    /// Return the archive to install a past version, downloaded in full like a new install.
+    pub fn get_version_archive(&self, version: &ArchiveVersion) -> GameArchive {
+        GameArchive {
+            link: Link {
+                local_path: self.link.local_path.to_owned(),
+                ..version.link.to_owned()
+            },
+            archive_format: version.archive_format,
+            path_to_executable: version
+                .path_to_executable
+                .to_owned()
+                .unwrap_or_else(|| self.path_to_executable.to_owned()),
+            patches: Vec::new(),
+            manifest_url: None,
+            download_size: version.download_size,
+            installed_size: version.installed_size,
+            versions: Vec::new(),
+            ..self.to_owned()
+        }
     }
 }
 
 #[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
 pub enum LinkType {
     BackgroundImage,
     NavigationIcon,
     GameArchiveLink,
 }
 
+/// An update available for an installed game, listed by the get_updates command.
+#[derive(Debug, Clone, Serialize)]
+pub struct GameUpdate {
+    pub game_id: u8,
+    pub title: String,
+    /// The version of the installed files, None if unknown.
+    pub installed_version: Option<String>,
+    /// The version of the latest revision.
+    pub version: String,
+    pub installed_revision: u64,
+    pub revision: u64,
+    /// The size (in bytes) to download, None if unknown.
+    pub download_size: Option<u64>,
+    /// The size (in bytes) of the game once updated, None if unknown.
+    pub installed_size: Option<u64>,
+}
+
 /// A struct that represents a game. It contains all the information needed to display the game in the launcher.
 #[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -133,6 +479,28 @@ pub struct Game {
     pub platform: Vec<String>,
     pub tags: Vec<String>,
     pub weight: u8,
+    /// The library folder the game is installed in, None for the default library (the app data directory).
+    #[serde(default)]
+    pub install_location: Option<PathBuf>,
+    /// The version of the installed files, None if not installed or unknown.
+    #[serde(default)]
+    pub installed_version: Option<String>,
+    /// Paths (relative to the install folder, e.g. "saves" or "config/settings.ini") declared by the player
+    /// that updates and repairs never overwrite nor delete.
+    #[serde(default)]
+    pub preserved_paths: Vec<String>,
+    /// The update policy chosen by the player for this game, None to follow the one of the settings.
+    #[serde(default)]
+    pub update_policy: Option<UpdatePolicy>,
+    /// The revision the player pinned the game to, None if not pinned. A pinned game is never updated automatically.
+    #[serde(default)]
+    pub pinned_revision: Option<u64>,
+    /// The revision kept in the rollback folder, None if no previous revision is kept.
+    #[serde(default)]
+    pub rollback_revision: Option<u64>,
+    /// The version kept in the rollback folder, None if unknown.
+    #[serde(default)]
+    pub rollback_version: Option<String>,
 }
 
 impl Game {
@@ -161,6 +529,13 @@ impl Game {
             platform,
             tags,
             weight,
+            install_location: None,
+            installed_version: None,
+            preserved_paths: Vec::new(),
+            update_policy: None,
+            pinned_revision: None,
+            rollback_revision: None,
+            rollback_version: None,
         }
     }
 
@@ -249,12 +624,10 @@ impl Game {
             && json["weight"].is_u64()
     }
 
-    /// Compare the local game with the remote game and perform the necessary actions to update the local game.
-    pub async fn update_game(
-        app: &AppHandle,
-        local_game: &mut Game,
-        remote_game: &Game,
-    ) -> errors::Result<()> {
+    /// Compare the local game with the remote game and update the local game metadata.<br>
+    /// No network I/O is performed here: the resources that must be downloaded are returned
+    /// so they can be fetched later with `Game::download_resources`.
+    pub fn update_game(local_game: &mut Game, remote_game: &Game) -> Vec<LinkType> {
         local_game.version = remote_game.version.to_owned();
         local_game.title = remote_game.title.to_owned();
         local_game.subtitle = remote_game.subtitle.to_owned();
@@ -266,6 +639,8 @@ impl Game {
         // lambda function to update a link
         // return true if the local link need to be downloaded
         let update_link = move |local_link: &mut Link, remote_link: &Link| {
+            // credentials requirements can change without a new revision
+            local_link.auth = remote_link.auth.to_owned();
             if local_link.revision < remote_link.revision {
                 // update the local link with the remote link
                 local_link.url = remote_link.url.to_owned();
@@ -285,49 +660,95 @@ impl Game {
             local_link.local_path.is_none()
         };
 
+        let mut outdated_links = Vec::new();
         if update_link(
             &mut local_game.background_image,
             &remote_game.background_image,
         ) {
-            local_game.download_link(app, BackgroundImage).await?;
+            outdated_links.push(BackgroundImage);
         }
         if update_link(
             &mut local_game.navigation_icon,
             &remote_game.navigation_icon,
         ) {
-            local_game.download_link(app, NavigationIcon).await?;
+            outdated_links.push(NavigationIcon);
         }
         // Don't download the download link because we want to let the user choose whether to download the game or not.
-        // Only say that the download link needs to be updated if the revision is different.
-        local_game.game_archive.need_update = update_link(
-            &mut local_game.game_archive.link,
-            &remote_game.game_archive.link,
-        );
+        // The installed files are kept so the update can be applied on top of them,
+        // only say that the game needs to be updated if the installed revision is older.
+        let local_archive = &mut local_game.game_archive;
+        let remote_archive = &remote_game.game_archive;
+        if local_archive.installed_revision.is_none() && local_archive.link.local_path.is_some() {
+            // Installs made before the installed revision was tracked are at the stored revision
+            local_archive.installed_revision = Some(local_archive.link.revision);
+        }
+        local_archive.link.url = remote_archive.link.url.to_owned();
+        local_archive.link.name = remote_archive.link.name.to_owned();
+        local_archive.link.revision = remote_archive.link.revision;
+        local_archive.link.auth = remote_archive.link.auth.to_owned();
+        local_archive.need_extract = remote_archive.need_extract;
+        local_archive.archive_format = remote_archive.archive_format;
+        local_archive.strip_top_level_folder = remote_archive.strip_top_level_folder;
+        local_archive.path_to_executable = remote_archive.path_to_executable.to_owned();
+        local_archive.helper_executables = remote_archive.helper_executables.to_owned();
+        local_archive.patches = remote_archive.patches.to_owned();
+        local_archive.manifest_url = remote_archive.manifest_url.to_owned();
+        local_archive.download_size = remote_archive.download_size;
+        local_archive.installed_size = remote_archive.installed_size;
+        local_archive.versions = remote_archive.versions.to_owned();
+        local_archive.need_update = local_archive
+            .installed_revision
+            .is_some_and(|revision| revision < local_archive.link.revision);
 
-        Ok(())
+        outdated_links
     }
 
-    /// Download the link and save it to the app's data directory. <br>
-    /// This function requires a mutable reference to the game struct to update the local path of the link.
-    async fn download_link(&mut self, app: &AppHandle, link_type: LinkType) -> errors::Result<()> {
-        // Get the folder name of the game using self as immutable reference before it gets borrowed as mutable.
-        let game_folder_name = self.get_folder_name();
+    /// Download the given resources of a game and save their local path in the local game list.<br>
+    /// The LOCAL_GAME_LIST lock is only held to read the game and to save each path, never during network I/O.
+    pub async fn download_resources(
+        app: &AppHandle,
+        game_id: u8,
+        link_types: Vec<LinkType>,
+    ) -> errors::Result<()> {
+        let game =
+            LOCAL_GAME_LIST
+                .read()
+                .await
+                .get(&game_id)
+                .cloned()
+                .ok_or(GameListFetchError(format!(
+                    "Game with id {} not found",
+                    game_id
+                )))?;
+        let resources_folder = game.get_resources_folder(app)?;
 
-        let link = match link_type {
-            BackgroundImage => &mut self.background_image,
-            NavigationIcon => &mut self.navigation_icon,
-            GameArchiveLink => &mut self.game_archive.link,
-        };
+        for link_type in link_types {
+            let local_path =
+                Self::download_link(app, game.get_link(link_type), &resources_folder).await?;
 
-        match reqwest::get(&link.url).await {
+            let mut game_list = LOCAL_GAME_LIST.write().await;
+            if let Some(local_game) = game_list.get_mut(&game_id) {
+                local_game.get_link_mut(link_type).local_path = Some(local_path);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Download the link and save it to the game data folder. <br>
+    /// Return the path of the downloaded file.
+    async fn download_link(
+        app: &AppHandle,
+        link: &Link,
+        game_data_folder: &Path,
+    ) -> errors::Result<PathBuf> {
+        let request = auth::authorize_request(app, link).await?;
+        match request.send().await {
             Ok(response) => {
-                if !response.status().is_success() {
-                    return Err(GameResourceDownloadError(format!("{:?}", response)));
-                }
+                auth::check_response(&response)?;
 
-                let game_data_folder = app.path().app_data_dir()?.join(game_folder_name);
-                let file_path = game_data_folder.join(&link.name);
-                fs::create_dir_all(&game_data_folder)?;
+                let file_path = paths::join_inside(game_data_folder, &link.name)?;
+                fs::create_dir_all(game_data_folder)?;
                 match File::create(&file_path) {
                     Ok(mut file) => {
                         let content = response.bytes().await?;
@@ -335,8 +756,6 @@ impl Game {
                         if let Err(e) = file.write_all(&content) {
                             return Err(Io(e));
                         }
-
-                        link.local_path = Some(file_path);
                     }
                     Err(e) => {
                         return Err(MessageError(format!(
@@ -347,7 +766,7 @@ impl Game {
                     }
                 }
 
-                Ok(())
+                Ok(file_path)
             }
             Err(e) => Err(GameResourceDownloadError(format!(
                 "Error downloading file \"{}\" : {:?}",
@@ -356,10 +775,224 @@ impl Game {
         }
     }
 
-    /// Return the folder name of this game based on its title.
+    fn get_link(&self, link_type: LinkType) -> &Link {
+        match link_type {
+            BackgroundImage => &self.background_image,
+            NavigationIcon => &self.navigation_icon,
+            GameArchiveLink => &self.game_archive.link,
+        }
+    }
+
+    fn get_link_mut(&mut self, link_type: LinkType) -> &mut Link {
+        match link_type {
+            BackgroundImage => &mut self.background_image,
+            NavigationIcon => &mut self.navigation_icon,
+            GameArchiveLink => &mut self.game_archive.link,
+        }
+    }
+
+    /// Return the folder where the files of this game are installed, in its library folder.
+    pub fn get_install_folder(&self, app: &AppHandle) -> errors::Result<PathBuf> {
+        let library_folder = match &self.install_location {
+            Some(install_location) => install_location.to_owned(),
+            None => app.path().app_data_dir()?,
+        };
+        Ok(library_folder.join(self.get_folder_name()))
+    }
+
+    /// Return the folder where the resources (images, icons) of this game are saved.<br>
+    /// They always stay in the app data directory, whatever the library the game is installed in.
+    pub fn get_resources_folder(&self, app: &AppHandle) -> errors::Result<PathBuf> {
+        Ok(app.path().app_data_dir()?.join(self.get_folder_name()))
+    }
+
+    /// Record the game as installed at the given revision, with its executable at `executable_path`.<br>
+    /// The version is only known when the latest revision is installed.
+    pub fn set_installed(&mut self, executable_path: PathBuf, revision: u64) {
+        let game_archive = &mut self.game_archive;
+        game_archive.link.local_path = Some(executable_path);
+        game_archive.installed_revision = Some(revision);
+        game_archive.need_update = revision < game_archive.link.revision;
+        self.installed_version =
+            (revision == game_archive.link.revision).then(|| self.version.to_owned());
+    }
+
+    /// Return the update available for this game, None if it is not installed, up to date or pinned to its revision.
+    pub fn get_update(&self) -> Option<GameUpdate> {
+        let game_archive = &self.game_archive;
+        if game_archive.link.local_path.is_none()
+            || !game_archive.need_update
+            || self.pinned_revision.is_some()
+        {
+            return None;
+        }
+        Some(GameUpdate {
+            game_id: self.id,
+            title: self.title.to_owned(),
+            installed_version: self.installed_version.to_owned(),
+            version: self.version.to_owned(),
+            installed_revision: game_archive.installed_revision?,
+            revision: game_archive.link.revision,
+            download_size: game_archive.get_update_size(),
+            installed_size: game_archive.installed_size,
+        })
+    }
+
+    /// Return the past version of the catalog the game is pinned to, None if it isn't pinned or follows the latest revision.
+    pub fn get_pinned_version(&self) -> Option<&ArchiveVersion> {
+        let pinned_revision = self.pinned_revision?;
+        if pinned_revision == self.game_archive.link.revision {
+            return None;
+        }
+        self.game_archive
+            .versions
+            .iter()
+            .find(|version| version.link.revision == pinned_revision)
+    }
+
+    /// Return true if the path (relative to the install folder, with "/" as separator) is preserved by the player,
+    /// either itself or one of its parent folders.
+    pub fn is_path_preserved(&self, relative_path: &str) -> bool {
+        self.preserved_paths.iter().any(|preserved_path| {
+            relative_path == preserved_path
+                || relative_path
+                    .strip_prefix(preserved_path.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+
+    /// Return the folder name of this game, based on its id so it never changes nor collides with another game.
     pub fn get_folder_name(&self) -> String {
+        format!("game_{}", self.id)
+    }
+
+    /// Return the folder name older launcher versions derived from the title, to move the folders of existing installs.
+    pub fn get_legacy_folder_name(&self) -> String {
         let mut folder_name = self.title.to_lowercase();
         folder_name.retain(|c| c.is_ascii_digit() || c.is_ascii_alphabetic());
         folder_name
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(name: &str, revision: u64) -> Link {
+        Link::new(
+            format!("https://example.com/{}", name),
+            name.to_string(),
+            revision,
+            None,
+            None,
+        )
+    }
+
+    fn patch(from_revision: u64, revision: u64, download_size: Option<u64>) -> ArchivePatch {
+        ArchivePatch {
+            from_revision,
+            link: link(&format!("{}-{}.patch", from_revision, revision), revision),
+            download_size,
+        }
+    }
+
+    fn game_archive(revision: u64, installed_revision: Option<u64>) -> GameArchive {
+        let mut game_archive = GameArchive::new(
+            link("game.zip", revision),
+            true,
+            false,
+            "game.exe".to_string(),
+            Vec::new(),
+            None,
+        );
+        game_archive.installed_revision = installed_revision;
+        game_archive
+    }
+
+    fn get_revisions(chain: &[ArchivePatch]) -> Vec<u64> {
+        chain.iter().map(|patch| patch.link.revision).collect()
+    }
+
+    #[test]
+    fn no_patch_chain_without_update() {
+        let mut game_archive = game_archive(3, None);
+        game_archive.patches = vec![patch(2, 3, None)];
+        assert!(game_archive.get_patch_chain().is_none());
+
+        game_archive.installed_revision = Some(3);
+        assert!(game_archive.get_patch_chain().is_none());
+    }
+
+    #[test]
+    fn patch_chain_takes_the_furthest_patches() {
+        let mut game_archive = game_archive(4, Some(1));
+        game_archive.patches = vec![
+            patch(1, 2, None),
+            patch(2, 3, None),
+            patch(3, 4, None),
+            patch(1, 3, None),
+        ];
+        let chain = game_archive.get_patch_chain().unwrap();
+        assert_eq!(get_revisions(&chain), vec![3, 4]);
+    }
+
+    #[test]
+    fn no_patch_chain_with_a_missing_patch() {
+        let mut game_archive = game_archive(4, Some(1));
+        game_archive.patches = vec![patch(1, 2, None), patch(3, 4, None)];
+        assert!(game_archive.get_patch_chain().is_none());
+
+        // A patch going past the latest revision is never applied
+        game_archive.patches = vec![patch(1, 5, None)];
+        assert!(game_archive.get_patch_chain().is_none());
+    }
+
+    #[test]
+    fn required_space_of_a_full_archive() {
+        let mut game_archive = game_archive(2, None);
+        game_archive.download_size = Some(100);
+        game_archive.installed_size = Some(300);
+        assert_eq!(game_archive.get_required_space(false), Some(400));
+
+        // The installed revision is moved aside when it is kept
+        game_archive.installed_revision = Some(1);
+        assert_eq!(game_archive.get_required_space(true), Some(400));
+
+        game_archive.need_extract = false;
+        assert_eq!(game_archive.get_required_space(false), Some(100));
+    }
+
+    #[test]
+    fn required_space_of_a_patch_chain() {
+        let mut game_archive = game_archive(3, Some(1));
+        game_archive.download_size = Some(100);
+        game_archive.installed_size = Some(300);
+        game_archive.patches = vec![patch(1, 2, Some(10)), patch(2, 3, Some(20))];
+        assert_eq!(game_archive.get_required_space(false), Some(30));
+        assert_eq!(game_archive.get_required_space(true), Some(330));
+
+        game_archive.patches[1].download_size = None;
+        assert_eq!(game_archive.get_required_space(false), None);
+    }
+
+    #[test]
+    fn required_space_of_a_manifest_install() {
+        let mut game_archive = game_archive(2, None);
+        game_archive.manifest_url = Some("https://example.com/manifest.json".to_string());
+        game_archive.installed_size = Some(300);
+        assert_eq!(game_archive.get_required_space(false), Some(300));
+
+        game_archive.installed_revision = Some(1);
+        assert_eq!(game_archive.get_required_space(false), Some(0));
+        assert_eq!(game_archive.get_required_space(true), Some(300));
+    }
+
+    #[test]
+    fn required_space_is_unknown_without_declared_sizes() {
+        let mut game_archive = game_archive(2, None);
+        assert_eq!(game_archive.get_required_space(false), None);
+
+        game_archive.download_size = Some(100);
+        assert_eq!(game_archive.get_required_space(false), None);
+    }
+}
@@ -0,0 +1,79 @@
+use crate::errors;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// The size of the buffer used to hash files with progress.
+const HASH_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Compute the SHA-256 hash of a file.<br>
+/// The hash is returned as a lowercase hexadecimal string, the format used by the catalog.
+pub fn sha256_file(path: &Path) -> errors::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Compute the SHA-256 hash of a file, calling `on_progress` with the number of bytes hashed after each read.
+pub fn sha256_file_with_progress(
+    path: &Path,
+    on_progress: &mut dyn FnMut(u64),
+) -> errors::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; HASH_BUFFER_SIZE];
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        hasher.update(&buffer[..read]);
+        on_progress(read as u64);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Copy a file to `target`, calling `on_progress` with the number of bytes copied after each read.<br>
+/// Return the SHA-256 hash of the data read, to verify the copy against it.
+pub fn copy_file_with_progress(
+    source: &Path,
+    target: &Path,
+    on_progress: &mut dyn FnMut(u64),
+) -> errors::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut source_file = File::open(source)?;
+    let mut target_file = File::create(target)?;
+    let mut buffer = vec![0; HASH_BUFFER_SIZE];
+    loop {
+        let read = match source_file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        hasher.update(&buffer[..read]);
+        target_file.write_all(&buffer[..read])?;
+        on_progress(read as u64);
+    }
+    target_file.sync_all()?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Compute the SHA-256 hash of a buffer, returned as a lowercase hexadecimal string.
+pub fn sha256_bytes(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Check that the file at `path` has the `expected` SHA-256 hash.
+pub fn verify_file(path: &Path, expected: &str) -> errors::Result<()> {
+    if !sha256_file(path)?.eq_ignore_ascii_case(expected) {
+        return Err(errors::Verror::GameResourceIntegrityError(
+            path.display().to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use crate::errors;
+use crate::settings::HttpSettings;
+use log::error;
+use std::fs;
+use std::sync::RwLock;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_http::reqwest::{Certificate, Client, Proxy};
+
+/// The HTTP client shared by every network path of the launcher.<br>
+/// It is held in the Tauri state and rebuilt each time the HTTP settings change.
+pub struct HttpClient(RwLock<Client>);
+
+impl HttpClient {
+    /// Build the client from the settings.<br>
+    /// Invalid settings are logged and replaced by the default ones so the launcher can still start.
+    pub fn new(settings: &HttpSettings) -> HttpClient {
+        let client = build_client(settings).unwrap_or_else(|e| {
+            error!("Invalid HTTP settings, using the default ones: {}", e);
+            build_client(&HttpSettings::default()).expect("Failed to build the default HTTP client")
+        });
+        HttpClient(RwLock::new(client))
+    }
+
+    /// Replace the client with one built from the new settings.
+    pub fn rebuild(&self, settings: &HttpSettings) -> errors::Result<()> {
+        let client = build_client(settings)?;
+        *self.0.write().unwrap() = client;
+        Ok(())
+    }
+
+    /// Return a handle to the client. Clones share the same connection pool.
+    pub fn get(&self) -> Client {
+        self.0.read().unwrap().clone()
+    }
+}
+
+/// Return the shared HTTP client from the Tauri state.
+pub fn get_client(app: &AppHandle) -> Client {
+    app.state::<HttpClient>().get()
+}
+
+fn build_client(settings: &HttpSettings) -> errors::Result<Client> {
+    let mut builder = Client::builder()
+        .user_agent(&settings.user_agent)
+        .connect_timeout(Duration::from_secs(settings.connect_timeout))
+        .read_timeout(Duration::from_secs(settings.read_timeout));
+
+    if let Some(proxy) = &settings.proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+    if let Some(certificate_path) = &settings.root_certificate {
+        builder =
+            builder.add_root_certificate(Certificate::from_pem(&fs::read(certificate_path)?)?);
+    }
+
+    Ok(builder.build()?)
+}
@@ -0,0 +1,118 @@
+use crate::download::GameDownload;
+use crate::errors::Verror::GameImportError;
+use crate::games::Game;
+use crate::install_manifest::InstallManifest;
+use crate::{errors, library, repair};
+use log::{error, info};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// # Import module
+/// Games can be registered as installed without being downloaded: from a folder where a build was unzipped by hand,
+/// or from an install the local game list has lost track of (e.g. the store was deleted with the launcher).
+/// The install manifest left in the folder by the launcher (`env::INSTALL_MANIFEST_FILE`) tells the game and the
+/// revision of the files, and their hashes.
+
+/// Check that the folder holds the game and return the install manifest of its files.<br>
+/// The files of a folder installed by the launcher are verified against its install manifest.
+/// Other folders are only checked for the executable of the game, and are recorded at `revision`
+/// (the latest revision of the catalog by default).
+pub fn check_game_folder(
+    game: &Game,
+    folder: &Path,
+    revision: Option<u64>,
+    download: &mut GameDownload,
+) -> errors::Result<InstallManifest> {
+    let executable_path = game.game_archive.get_executable_path(folder)?;
+    if !executable_path.is_file() {
+        return Err(GameImportError(format!(
+            "{}: the executable \"{}\" is missing",
+            game.title,
+            executable_path.display()
+        )));
+    }
+
+    match InstallManifest::load(folder) {
+        Some(install_manifest) if install_manifest.game_id == game.id => {
+            let report = repair::verify_install(folder, game, &install_manifest, download)?;
+            if !report.missing.is_empty() || !report.modified.is_empty() {
+                return Err(GameImportError(format!(
+                    "{}: {} files are missing and {} are modified, repair the game after downloading it instead",
+                    game.title,
+                    report.missing.len(),
+                    report.modified.len()
+                )));
+            }
+            Ok(install_manifest)
+        }
+        Some(install_manifest) => Err(GameImportError(format!(
+            "{}: the folder holds the game with id {}",
+            game.title, install_manifest.game_id
+        ))),
+        None => Ok(InstallManifest::scan(
+            folder,
+            game,
+            revision.unwrap_or(game.game_archive.link.revision),
+        )?
+        .exclude_preserved_paths(game)),
+    }
+}
+
+/// Register as installed the games found in the library folders but missing from the local game list.<br>
+/// Only the install folders with an install manifest of the game and its executable are adopted.
+/// The files are not hashed here to keep the startup fast, `verify_game` can be used for that.
+pub fn adopt_installs(app: &AppHandle, game_list: &mut HashMap<u8, Game>) {
+    let library_folders = match library::get_library_folders(app) {
+        Ok(library_folders) => library_folders,
+        Err(e) => {
+            error!("Error getting the library folders: {:?}", e);
+            return;
+        }
+    };
+
+    for game in game_list
+        .values_mut()
+        .filter(|game| game.game_archive.link.local_path.is_none())
+    {
+        for (index, library_folder) in library_folders.iter().enumerate() {
+            let install_folder = library_folder.join(game.get_folder_name());
+            let Some(install_manifest) = InstallManifest::load(&install_folder) else {
+                continue;
+            };
+            let Ok(executable_path) = game.game_archive.get_executable_path(&install_folder) else {
+                continue;
+            };
+            if install_manifest.game_id != game.id || !executable_path.is_file() {
+                continue;
+            }
+
+            info!(
+                "Adopting the install of {} found in \"{}\"",
+                game.title,
+                install_folder.display()
+            );
+            // The first library folder is the default one
+            game.install_location = (index > 0).then(|| library_folder.to_owned());
+            game.set_installed(executable_path, install_manifest.revision);
+            break;
+        }
+    }
+}
+
+/// Return the library folder holding `folder` as the install folder of the game, if any.<br>
+/// The paths are compared once canonicalized, so a trailing separator or the case of a drive letter don't matter.
+pub fn find_library_folder(
+    app: &AppHandle,
+    game: &Game,
+    folder: &Path,
+) -> errors::Result<Option<PathBuf>> {
+    let folder = fs::canonicalize(folder)?;
+    Ok(library::get_library_folders(app)?
+        .into_iter()
+        .find(|library_folder| {
+            fs::canonicalize(library_folder.join(game.get_folder_name()))
+                .is_ok_and(|install_folder| install_folder == folder)
+        }))
+}
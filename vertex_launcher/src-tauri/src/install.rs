@@ -0,0 +1,438 @@
+use crate::errors::Verror::{GameResourceExtractionError, GameUninstallError};
+use crate::games::Game;
+use crate::install_manifest::InstallManifest;
+use crate::manifest::list_files;
+use crate::{archive, env, errors, library, paths};
+use log::{error, info};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// # Installs module
+/// Full installs and updates are never extracted in the install folder of the game.
+/// They are extracted in a staging folder next to it, verified there, and then swapped in:
+/// 1. The install folder is renamed to its previous folder.
+/// 2. The staging folder is renamed to the install folder.
+/// 3. The previous folder is deleted.
+///
+/// A crash at any point leaves either the old or the new install in place. The leftover folders
+/// are cleaned on the next startup by `clean_leftover_folders`.
+
+/// The suffix of the folder where a new version of an install is extracted.
+const STAGING_SUFFIX: &str = "vertex_staging";
+
+/// The suffix of the folder where the old version of an install is kept during the swap.
+const PREVIOUS_SUFFIX: &str = "vertex_previous";
+
+/// The suffix of the folder where the previous revision of an install is kept, to roll back a broken update.
+const ROLLBACK_SUFFIX: &str = "vertex_rollback";
+
+/// Return the folder where the new version of the install is extracted.
+pub fn get_staging_folder(install_folder: &Path) -> PathBuf {
+    get_sibling_folder(install_folder, STAGING_SUFFIX)
+}
+
+fn get_previous_folder(install_folder: &Path) -> PathBuf {
+    get_sibling_folder(install_folder, PREVIOUS_SUFFIX)
+}
+
+/// Return the folder where the previous revision of the install is kept.
+pub fn get_rollback_folder(install_folder: &Path) -> PathBuf {
+    get_sibling_folder(install_folder, ROLLBACK_SUFFIX)
+}
+
+fn get_sibling_folder(install_folder: &Path, suffix: &str) -> PathBuf {
+    let mut folder = install_folder.as_os_str().to_owned();
+    folder.push(".");
+    folder.push(suffix);
+    PathBuf::from(folder)
+}
+
+/// Create an empty staging folder for the install, removing the one left by an interrupted install.
+pub fn prepare_staging_folder(install_folder: &Path) -> io::Result<PathBuf> {
+    let staging_folder = get_staging_folder(install_folder);
+    if staging_folder.exists() {
+        fs::remove_dir_all(&staging_folder)?;
+    }
+    fs::create_dir_all(&staging_folder)?;
+    Ok(staging_folder)
+}
+
+/// Check that the staged install is complete enough to be launched.
+pub fn verify_staged_install(staged_executable: &Path) -> errors::Result<()> {
+    if !staged_executable.is_file() {
+        return Err(GameResourceExtractionError(format!(
+            "the executable \"{}\" is missing from the archive",
+            staged_executable.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Allow the file to be executed. Archives made on Windows don't store any permission.
+#[cfg(unix)]
+pub fn make_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o111);
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(not(unix))]
+pub fn make_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Make the executable of the game and its helper binaries executable.<br>
+/// Return the path of the game executable.
+pub fn make_game_executable(game: &Game, install_folder: &Path) -> errors::Result<PathBuf> {
+    let executable_path = game.game_archive.get_executable_path(install_folder)?;
+    make_executable(&executable_path)?;
+    for helper_executable in &game.game_archive.helper_executables {
+        let helper_path = paths::join_inside(install_folder, helper_executable)?;
+        if let Err(e) = make_executable(&helper_path) {
+            error!(
+                "Error making the helper \"{}\" executable: {:?}",
+                helper_executable, e
+            );
+        }
+    }
+    Ok(executable_path)
+}
+
+/// Move to the staging folder the files of the current install that the new version doesn't contain,
+/// such as the launcher resources and the files created by the game.<br>
+/// The files of the current revision, listed by its install manifest, and the `excluded_files`
+/// (relative to the install folder) are left behind.<br>
+/// Return the moved files, to put them back with `put_back_files` if the swap fails.
+pub fn carry_over_files(
+    install_folder: &Path,
+    staging_folder: &Path,
+    excluded_files: &[PathBuf],
+) -> io::Result<Vec<PathBuf>> {
+    let mut moved_files = Vec::new();
+    if !install_folder.is_dir() {
+        return Ok(moved_files);
+    }
+
+    let mut game_files: HashSet<PathBuf> = excluded_files.iter().cloned().collect();
+    if let Some(install_manifest) = InstallManifest::load_trusted(install_folder) {
+        game_files.extend(
+            install_manifest
+                .files
+                .into_iter()
+                .map(|file| PathBuf::from(file.path)),
+        );
+    }
+    for relative_path in list_files(install_folder)? {
+        let target = staging_folder.join(&relative_path);
+        if target.exists() || game_files.contains(&relative_path) {
+            continue;
+        }
+        let moved = match target.parent() {
+            Some(parent) => fs::create_dir_all(parent),
+            None => Ok(()),
+        }
+        .and_then(|_| fs::rename(install_folder.join(&relative_path), &target));
+        if let Err(e) = moved {
+            put_back_files(install_folder, staging_folder, &moved_files);
+            return Err(e);
+        }
+        moved_files.push(relative_path);
+    }
+    Ok(moved_files)
+}
+
+/// Move the files carried over to the staging folder back to the current install.
+pub fn put_back_files(install_folder: &Path, staging_folder: &Path, moved_files: &[PathBuf]) {
+    for relative_path in moved_files {
+        if let Err(e) = fs::rename(
+            staging_folder.join(relative_path),
+            install_folder.join(relative_path),
+        ) {
+            error!(
+                "Error putting \"{}\" back in the install: {:?}",
+                relative_path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Remove from the staging folder the paths preserved by the player that exist in the current install,
+/// so `carry_over_files` brings the version of the player into the new install.
+pub fn drop_preserved_paths(
+    game: &Game,
+    install_folder: &Path,
+    staging_folder: &Path,
+) -> errors::Result<()> {
+    for preserved_path in &game.preserved_paths {
+        if !paths::join_inside(install_folder, preserved_path)?.exists() {
+            continue;
+        }
+        let staged_path = paths::join_inside(staging_folder, preserved_path)?;
+        match fs::symlink_metadata(&staged_path) {
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(&staged_path)?,
+            Ok(_) => fs::remove_file(&staged_path)?,
+            Err(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Replace the install folder with the staging folder.<br>
+/// The current install is only deleted once the new one is in place, and restored if the swap fails.
+/// If `keep_previous` is true, the current install is moved to the rollback folder instead of being deleted.
+pub fn swap_install(
+    staging_folder: &Path,
+    install_folder: &Path,
+    keep_previous: bool,
+) -> errors::Result<()> {
+    let previous_folder = get_previous_folder(install_folder);
+    if previous_folder.exists() {
+        fs::remove_dir_all(&previous_folder)?;
+    }
+
+    let has_previous_install = install_folder.exists();
+    if has_previous_install {
+        fs::rename(install_folder, &previous_folder)?;
+    }
+
+    if let Err(e) = fs::rename(staging_folder, install_folder) {
+        if has_previous_install {
+            if let Err(restore_error) = fs::rename(&previous_folder, install_folder) {
+                error!(
+                    "Error restoring the previous install \"{}\": {:?}",
+                    install_folder.display(),
+                    restore_error
+                );
+            }
+        }
+        return Err(e.into());
+    }
+
+    if has_previous_install && keep_previous {
+        let rollback_folder = get_rollback_folder(install_folder);
+        if rollback_folder.exists() {
+            fs::remove_dir_all(&rollback_folder)?;
+        }
+        fs::rename(&previous_folder, &rollback_folder)?;
+    } else if has_previous_install {
+        if let Err(e) = fs::remove_dir_all(&previous_folder) {
+            // It will be removed on the next startup
+            error!(
+                "Error deleting the previous install \"{}\": {:?}",
+                previous_folder.display(),
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Copy the install to its rollback folder before it is updated in place. The resources of the game are left out,
+/// and so are the files created by the player (e.g. saves) when the install manifest tells them apart.<br>
+/// The copy is made in a staging folder first, so an interrupted copy is never taken for a complete install.
+pub fn keep_previous_install(game: &Game, install_folder: &Path) -> errors::Result<()> {
+    let rollback_folder = get_rollback_folder(install_folder);
+    let staging_folder = prepare_staging_folder(&rollback_folder)?;
+    info!(
+        "Keeping the previous install of {} in \"{}\"",
+        game.title,
+        rollback_folder.display()
+    );
+
+    let resources = [
+        game.background_image.name.as_str(),
+        game.navigation_icon.name.as_str(),
+    ];
+    let relative_paths: Vec<PathBuf> = match InstallManifest::load_trusted(install_folder) {
+        Some(install_manifest) => install_manifest
+            .files
+            .into_iter()
+            .map(|file| PathBuf::from(file.path))
+            .chain([PathBuf::from(env::INSTALL_MANIFEST_FILE)])
+            .filter(|relative_path| {
+                fs::symlink_metadata(install_folder.join(relative_path)).is_ok()
+            })
+            .collect(),
+        None => list_files(install_folder)?
+            .into_iter()
+            .filter(|relative_path| {
+                !resources.contains(&relative_path.to_string_lossy().replace('\\', "/").as_str())
+            })
+            .collect(),
+    };
+    for relative_path in relative_paths {
+        let source = paths::join_inside(install_folder, &relative_path)?;
+        let target = staging_folder.join(&relative_path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::symlink_metadata(&source)?.file_type().is_symlink() {
+            archive::create_symlink(&fs::read_link(&source)?, &target)?;
+        } else {
+            fs::copy(&source, &target)?;
+        }
+    }
+
+    if rollback_folder.exists() {
+        fs::remove_dir_all(&rollback_folder)?;
+    }
+    fs::rename(&staging_folder, &rollback_folder)?;
+    Ok(())
+}
+
+/// Swap the previous revision kept in the rollback folder back in place of the current install.<br>
+/// The files that are not part of the current revision (e.g. saves) and the paths preserved by the player
+/// are carried over to the previous revision, replacing the copies kept with it.
+pub fn restore_previous_install(game: &Game, install_folder: &Path) -> errors::Result<()> {
+    let rollback_folder = get_rollback_folder(install_folder);
+    drop_preserved_paths(game, install_folder, &rollback_folder)?;
+    // Without a trusted install manifest, every file could belong to the current revision and the kept copies win
+    if let Some(install_manifest) = InstallManifest::load_trusted(install_folder) {
+        let game_files: HashSet<PathBuf> = install_manifest
+            .files
+            .into_iter()
+            .map(|file| PathBuf::from(file.path))
+            .chain([PathBuf::from(env::INSTALL_MANIFEST_FILE)])
+            .collect();
+        for relative_path in list_files(install_folder)? {
+            let kept_copy = rollback_folder.join(&relative_path);
+            if !game_files.contains(&relative_path) && fs::symlink_metadata(&kept_copy).is_ok() {
+                fs::remove_file(&kept_copy)?;
+            }
+        }
+    }
+    let moved_files = carry_over_files(install_folder, &rollback_folder, &[])?;
+    swap_install(&rollback_folder, install_folder, false)
+        .inspect_err(|_| put_back_files(install_folder, &rollback_folder, &moved_files))
+}
+
+/// Delete the previous revision kept in the rollback folder, if any.
+pub fn remove_previous_install(install_folder: &Path) -> io::Result<()> {
+    let rollback_folder = get_rollback_folder(install_folder);
+    if rollback_folder.exists() {
+        fs::remove_dir_all(&rollback_folder)?;
+    }
+    Ok(())
+}
+
+/// Delete the installed files of a game. The launcher resources of the game are kept.<br>
+/// When `keep_save_data` is true, only the files listed by the install manifest are deleted, so the files
+/// created by the player or the game (e.g. its saves) are kept. An install without manifest, or with a scanned one,
+/// can't tell them apart: its whole install folder is deleted, and keeping the save data is refused.
+pub fn remove_install(
+    install_folder: &Path,
+    game: &Game,
+    keep_save_data: bool,
+) -> errors::Result<()> {
+    let files = match InstallManifest::load_trusted(install_folder) {
+        Some(install_manifest) if keep_save_data => install_manifest
+            .files
+            .iter()
+            .map(|file| paths::join_inside(install_folder, &file.path))
+            .collect::<errors::Result<Vec<PathBuf>>>()?,
+        None if keep_save_data => {
+            return Err(GameUninstallError(format!(
+                "{}: the game files can't be told apart from the save data, uninstall it without keeping the save data",
+                game.title
+            )))
+        }
+        _ => {
+            let resources = [
+                game.background_image.name.as_str(),
+                game.navigation_icon.name.as_str(),
+            ];
+            list_files(install_folder)?
+                .into_iter()
+                .filter(|relative_path| {
+                    !resources
+                        .contains(&relative_path.to_string_lossy().replace('\\', "/").as_str())
+                })
+                .map(|relative_path| install_folder.join(relative_path))
+                .collect()
+        }
+    };
+
+    info!(
+        "Removing {} files from \"{}\"",
+        files.len(),
+        install_folder.display()
+    );
+    let install_manifest_path = install_folder.join(env::INSTALL_MANIFEST_FILE);
+    for file in files.iter().chain([&install_manifest_path]) {
+        match fs::remove_file(file) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    remove_empty_folders(install_folder)?;
+    Ok(())
+}
+
+/// Remove the empty sub-folders of the folder, deepest first. The folder itself is kept.
+fn remove_empty_folders(folder: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.is_dir() && !path.is_symlink() {
+            remove_empty_folders(&path)?;
+            if fs::read_dir(&path)?.next().is_none() {
+                fs::remove_dir(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Remove the staging folders left by interrupted installs in every library folder.<br>
+/// If the launcher stopped in the middle of a swap, the previous install is restored when the new one
+/// isn't in place, and deleted otherwise.
+pub fn clean_leftover_folders(app: &AppHandle) {
+    let library_folders = match library::get_library_folders(app) {
+        Ok(library_folders) => library_folders,
+        Err(e) => {
+            error!("Error getting the library folders: {:?}", e);
+            return;
+        }
+    };
+
+    for entry in library_folders
+        .iter()
+        .filter_map(|library_folder| fs::read_dir(library_folder).ok())
+        .flat_map(|entries| entries.flatten())
+    {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let Some(extension) = path.extension().and_then(|extension| extension.to_str()) else {
+            continue;
+        };
+        let install_folder = path.with_extension("");
+
+        let result = match extension {
+            STAGING_SUFFIX => {
+                info!("Removing interrupted install \"{}\"", path.display());
+                fs::remove_dir_all(&path)
+            }
+            PREVIOUS_SUFFIX if install_folder.exists() => {
+                info!("Removing previous install \"{}\"", path.display());
+                fs::remove_dir_all(&path)
+            }
+            PREVIOUS_SUFFIX => {
+                info!(
+                    "Restoring previous install \"{}\"",
+                    install_folder.display()
+                );
+                fs::rename(&path, &install_folder)
+            }
+            _ => continue,
+        };
+        if let Err(e) = result {
+            error!("Error cleaning \"{}\": {:?}", path.display(), e);
+        }
+    }
+}
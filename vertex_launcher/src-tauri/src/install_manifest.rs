@@ -0,0 +1,176 @@
+use crate::games::Game;
+use crate::hash::sha256_file;
+use crate::manifest::{list_files, ContentManifest};
+use crate::patch::PatchedFile;
+use crate::{env, errors, paths};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// # Install manifests module
+/// An install manifest lists every file written by the launcher for a game, with its size and hash.
+/// It is written after every install or update in the install folder (`env::INSTALL_MANIFEST_FILE`),
+/// so the files of the game can be told apart from the files created by the player or the game itself.<br>
+/// The manifests built by scanning an install made without one can't tell them apart: they are marked as `scanned`,
+/// and only trusted to verify the install, never to decide which files belong to the player.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallManifest {
+    pub game_id: u8,
+    /// The revision of the installed files.
+    pub revision: u64,
+    pub files: Vec<InstalledFile>,
+    /// True if the manifest was built by scanning the install folder, so it can list files created by the player.
+    #[serde(default)]
+    pub scanned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledFile {
+    /// Path of the file relative to the install folder, with "/" as separator.
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl InstallManifest {
+    /// Build the manifest of the given files of the install folder, hashing each of them.
+    pub fn from_files(
+        install_folder: &Path,
+        game_id: u8,
+        revision: u64,
+        relative_paths: &[PathBuf],
+    ) -> errors::Result<InstallManifest> {
+        let mut files = Vec::with_capacity(relative_paths.len());
+        for relative_path in relative_paths {
+            let path = install_folder.join(relative_path);
+            files.push(InstalledFile {
+                path: to_manifest_path(relative_path),
+                size: fs::metadata(&path)?.len(),
+                sha256: sha256_file(&path)?,
+            });
+        }
+        Ok(InstallManifest {
+            game_id,
+            revision,
+            files,
+            scanned: false,
+        })
+    }
+
+    /// Build the manifest of an install made from a content manifest, whose files are already hashed.
+    pub fn from_content_manifest(
+        game_id: u8,
+        content_manifest: &ContentManifest,
+    ) -> InstallManifest {
+        InstallManifest {
+            game_id,
+            revision: content_manifest.revision,
+            files: content_manifest
+                .files
+                .iter()
+                .map(|file| InstalledFile {
+                    path: file.path.to_owned(),
+                    size: file.size,
+                    sha256: file.sha256.to_lowercase(),
+                })
+                .collect(),
+            scanned: false,
+        }
+    }
+
+    /// Update the manifest with the files changed by delta patches.
+    pub fn apply_patches(
+        &mut self,
+        install_folder: &Path,
+        revision: u64,
+        patched_files: &[PatchedFile],
+    ) -> errors::Result<()> {
+        let mut files: BTreeMap<String, InstalledFile> = self
+            .files
+            .drain(..)
+            .map(|file| (file.path.to_owned(), file))
+            .collect();
+        for patched_file in patched_files {
+            match &patched_file.sha256 {
+                Some(sha256) => {
+                    let path = paths::join_inside(install_folder, &patched_file.path)?;
+                    files.insert(
+                        patched_file.path.to_owned(),
+                        InstalledFile {
+                            path: patched_file.path.to_owned(),
+                            size: fs::metadata(path)?.len(),
+                            sha256: sha256.to_lowercase(),
+                        },
+                    );
+                }
+                None => {
+                    files.remove(&patched_file.path);
+                }
+            }
+        }
+
+        self.files = files.into_values().collect();
+        self.revision = revision;
+        Ok(())
+    }
+
+    /// Remove the paths preserved by the player from the manifest, as their content belongs to the player.
+    pub fn exclude_preserved_paths(mut self, game: &Game) -> InstallManifest {
+        self.files
+            .retain(|file| !game.is_path_preserved(&file.path));
+        self
+    }
+
+    /// Return the manifest saved in the install folder, None if the game was installed without one.
+    pub fn load(install_folder: &Path) -> Option<InstallManifest> {
+        let content = fs::read(install_folder.join(env::INSTALL_MANIFEST_FILE)).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    /// Return the manifest saved in the install folder if it only lists the files of the game,
+    /// None if the game was installed without one or if it was scanned.
+    pub fn load_trusted(install_folder: &Path) -> Option<InstallManifest> {
+        InstallManifest::load(install_folder).filter(|install_manifest| !install_manifest.scanned)
+    }
+
+    /// Save the manifest in the install folder.
+    pub fn save(&self, install_folder: &Path) -> errors::Result<()> {
+        fs::write(
+            install_folder.join(env::INSTALL_MANIFEST_FILE),
+            serde_json::to_vec(self)?,
+        )?;
+        Ok(())
+    }
+
+    /// Build the manifest of an install made without one, from every file of the install folder.<br>
+    /// The files written by the launcher itself (the resources of the game) are left out. The files
+    /// created by the player can't be, so the manifest is marked as scanned.
+    pub fn scan(
+        install_folder: &Path,
+        game: &Game,
+        revision: u64,
+    ) -> errors::Result<InstallManifest> {
+        let launcher_files = [
+            env::INSTALL_MANIFEST_FILE,
+            game.background_image.name.as_str(),
+            game.navigation_icon.name.as_str(),
+        ];
+        let relative_paths: Vec<PathBuf> = list_files(install_folder)?
+            .into_iter()
+            .filter(|relative_path| {
+                !launcher_files.contains(&to_manifest_path(relative_path).as_str())
+            })
+            .collect();
+        let mut install_manifest =
+            InstallManifest::from_files(install_folder, game.id, revision, &relative_paths)?;
+        install_manifest.scanned = true;
+        Ok(install_manifest)
+    }
+}
+
+/// Convert a relative path to the format used in manifests, the same on every platform.
+fn to_manifest_path(relative_path: &Path) -> String {
+    relative_path.to_string_lossy().replace('\\', "/")
+}
@@ -1,23 +1,40 @@
 #![allow(unused_doc_comments)]
 
 use crate::env::LOCAL_GAME_LIST;
-use crate::games::Game;
+use crate::games::{Game, LinkType};
+use futures_util::stream::{self, StreamExt};
 use log::{error, info};
 use serde_json::json;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tauri::{App, AppHandle, Builder, Emitter, Manager, RunEvent, Window, WindowEvent, Wry};
 use tauri_plugin_fs::FsExt;
-use tauri_plugin_http::reqwest;
 use tauri_plugin_log::Target;
 use tauri_plugin_store::{JsonValue, Store, StoreExt};
 
+mod archive;
+mod auth;
+mod cache;
 mod commands;
 mod download;
 mod env;
 mod errors;
 mod games;
+mod hash;
+mod http;
+mod import;
+mod install;
+mod install_manifest;
+mod library;
+mod manifest;
+mod patch;
+mod paths;
+mod queue;
+mod repair;
+mod settings;
 mod system_tray;
+mod updates;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -100,7 +117,24 @@ pub fn run() {
         commands::get_game_list,
         commands::get_game,
         commands::download,
+        commands::install_version,
+        commands::get_updates,
+        commands::update_game,
+        commands::update_all,
+        commands::set_preserved_paths,
+        commands::set_update_policy,
+        commands::rollback_game,
+        commands::unpin_game,
         commands::launch,
+        commands::uninstall,
+        commands::verify_game,
+        commands::repair_game,
+        commands::move_game,
+        commands::import_game,
+        commands::get_settings,
+        commands::set_settings,
+        commands::set_credentials,
+        commands::remove_credentials,
     ]);
 
     ///### Application building
@@ -145,9 +179,14 @@ fn frontend_event_handler(window: &Window, event: &WindowEvent) -> errors::Resul
 /// Perform the application setup
 /// run on startup
 fn application_setup(app: &mut App) -> errors::Result<()> {
-    // Allow the app to access the app directory only
+    // Allow the app to access the app directory and the library folders registered by the player only
     app.fs_scope()
         .allow_directory(app.handle().path().app_data_dir()?, true)?;
+    let settings = settings::load_settings(app.handle());
+    library::allow_library_folders(app.handle(), &settings.library_folders)?;
+
+    // Build the HTTP client shared by every network path from the player settings
+    app.manage(http::HttpClient::new(&settings.http));
 
     // Set up the system tray
     match system_tray::setup_system_tray(app) {
@@ -196,6 +235,12 @@ fn application_setup(app: &mut App) -> errors::Result<()> {
                     if store.get(env::STORE_LOCAL_GAME_LIST_KEY).is_none() {
                         store.set(env::STORE_LOCAL_GAME_LIST_KEY, json!([]));
                     }
+                    if store.get(env::STORE_SETTINGS_KEY).is_none() {
+                        store.set(
+                            env::STORE_SETTINGS_KEY,
+                            serde_json::to_value(settings::Settings::default()).unwrap(),
+                        );
+                    }
                     info!("Store has been initialized.");
                 }
                 Err(e) => {
@@ -207,6 +252,14 @@ fn application_setup(app: &mut App) -> errors::Result<()> {
         }
         // At this point the store variable is initialized and can be used.
 
+        /// ### Clean interrupted installs
+        /// Remove the staging folders left by installs interrupted by the previous session,
+        /// and finish the swaps that were in progress.
+        {
+            info!("- Cleaning interrupted installs...");
+            install::clean_leftover_folders(&app_handle);
+        }
+
         /// ### Fetch the remote games list
         /// Fetch the remote games list from the ONLINE_CONFIGURATION_FILE URL
         /// and save it to the store.
@@ -222,7 +275,10 @@ fn application_setup(app: &mut App) -> errors::Result<()> {
                 }
             };
 
-            let result = reqwest::get(env::ONLINE_CONFIGURATION_FILE).await;
+            let result = http::get_client(&app_handle)
+                .get(env::ONLINE_CONFIGURATION_FILE)
+                .send()
+                .await;
             match result {
                 Ok(response) => {
                     if !response.status().is_success() {
@@ -286,17 +342,21 @@ fn application_setup(app: &mut App) -> errors::Result<()> {
                 error!("Error loading local games list: {:?}", e);
                 HashMap::new()
             });
+            // Move the folders named after the stored titles, before the catalog renames any game
+            library::migrate_legacy_folders(&app_handle, &mut global_local_game_list);
 
             info!("Local games list loaded successfully.");
         }
 
-        /// ### Download games resources
+        /// ### Reconcile the games list
         /// For each game in the remote games list :
         /// - Add them in the local games list if they are not already there.
-        /// - Check if local file are at the latest revision and download them if not.
-        /// - Save the game to the store.
-        {
-            info!("- Downloading games resources...");
+        /// - Update the local metadata and find the resources that are not at the latest revision.
+        /// - Save the games to the store.
+        ///
+        /// No network I/O is done here so the LOCAL_GAME_LIST lock is held for a short time only.
+        let outdated_resources = {
+            info!("- Reconciling games list...");
             let distant_game_list = {
                 match store.get(env::STORE_REMOTE_GAME_LIST_KEY) {
                     Some(remote_game_list) => {
@@ -311,6 +371,7 @@ fn application_setup(app: &mut App) -> errors::Result<()> {
             };
 
             let mut global_local_game_list = LOCAL_GAME_LIST.write().await;
+            let mut outdated_resources: Vec<(u8, Vec<LinkType>)> = Vec::new();
 
             for raw_remote_game in distant_game_list {
                 let remote_game = match Game::initialize_game_from_json(&raw_remote_game) {
@@ -325,47 +386,32 @@ fn application_setup(app: &mut App) -> errors::Result<()> {
                     }
                 };
 
-                if global_local_game_list.contains_key(&remote_game.id) {
-                    // The game is already in the local list then only update it
-                    let local_game = global_local_game_list.get_mut(&remote_game.id).unwrap();
-                    if let Err(e) = Game::update_game(&app_handle, local_game, &remote_game).await {
-                        error!(
-                            "Error updating game resources of {}: {:?}",
-                            remote_game.title, e
-                        );
-                    };
-                } else {
-                    // The game is not in the local list then download resources and add
-                    // the game to the local list
-                    let mut new_game = remote_game.clone();
-                    if let Err(e) =
-                        Game::update_game(&app_handle, &mut new_game, &remote_game).await
-                    {
-                        error!(
-                            "Error updating game resources of {}: {:?}",
-                            new_game.title, e
-                        );
-                    }
-
-                    global_local_game_list.insert(new_game.id, new_game);
+                // Games that are not in the local list yet are added with all their resources to download
+                let local_game = global_local_game_list
+                    .entry(remote_game.id)
+                    .or_insert_with(|| remote_game.clone());
+                let outdated_links = Game::update_game(local_game, &remote_game);
+                if !outdated_links.is_empty() {
+                    outdated_resources.push((remote_game.id, outdated_links));
                 }
-
-                info!(
-                    "Game resources of {} has been downloaded successfully.",
-                    remote_game.title
-                );
             }
 
+            // Re-adopt the installs of the library folders missing from the local games list
+            import::adopt_installs(&app_handle, &mut global_local_game_list);
+
             // Save the local games list to the store
             store.set(
                 env::STORE_LOCAL_GAME_LIST_KEY,
                 serde_json::to_value(&*global_local_game_list).unwrap(),
             );
-        }
+
+            outdated_resources
+        };
 
         /// ### End of initialization
-        /// After all the initialization tasks are done, emit the app_initialized event.
+        /// As soon as the games list is known, emit the app_initialized event.
         /// Then close the splashscreen and show the main window.
+        /// The resources are downloaded afterward and fill in the main window as they arrive.
         info!("[+] Done initializing.");
         // Emit the app_initialized event
         match app_handle.emit(env::EVENT_INIT, ()) {
@@ -375,18 +421,85 @@ fn application_setup(app: &mut App) -> errors::Result<()> {
             }
         };
 
-        // TEMP: download the first game to test the download progress
-        // match commands::download(app_handle.clone(), 1).await {
-        //     Ok(_) => {}
-        //     Err(e) => {
-        //         error!("Error downloading game: {:?}", e);
-        //     }
-        // }
-
         // After it's done, close the splashscreen and display the main window
         let _ = splashscreen_window.close();
         let _ = main_window.show();
         let _ = system_tray::update_tray_menu(main_window.app_handle());
+
+        // Resume the downloads interrupted by the previous session, now that the games list is known
+        queue::resume_downloads(&app_handle);
+        // Then act on the updates found in the catalog according to the update policy of each game
+        updates::apply_update_policies(&app_handle).await;
+
+        /// ### Download games resources
+        /// Download the images and icons of every game concurrently, up to `env::RESOURCE_DOWNLOAD_CONCURRENCY` at a time.
+        /// Each time the resources of a game are downloaded, advertise the progress and the new games list to the frontend.
+        {
+            info!("- Downloading games resources...");
+            let total = outdated_resources.len();
+            let completed = AtomicUsize::new(0);
+
+            stream::iter(outdated_resources)
+                .for_each_concurrent(
+                    env::RESOURCE_DOWNLOAD_CONCURRENCY,
+                    |(game_id, outdated_links)| {
+                        let app_handle = &app_handle;
+                        let completed = &completed;
+                        async move {
+                            // The error is logged right away as it can't be kept across an await point
+                            let success = match Game::download_resources(
+                                app_handle,
+                                game_id,
+                                outdated_links,
+                            )
+                            .await
+                            {
+                                Ok(_) => {
+                                    info!(
+                                        "Game resources of {} has been downloaded successfully.",
+                                        game_id
+                                    );
+                                    true
+                                }
+                                Err(e) => {
+                                    error!(
+                                        "Error downloading game resources of {}: {:?}",
+                                        game_id, e
+                                    );
+                                    false
+                                }
+                            };
+
+                            let completed = completed.fetch_add(1, Ordering::Relaxed) + 1;
+                            let _ = app_handle.emit(
+                                env::EVENT_GAME_RESOURCES_PROGRESS,
+                                json!({
+                                    "game_id": game_id,
+                                    "success": success,
+                                    "completed": completed,
+                                    "total": total,
+                                }),
+                            );
+                            match commands::get_game_list().await {
+                                Ok(game_list) => {
+                                    let _ =
+                                        app_handle.emit(env::EVENT_GAME_LIST_UPDATED, game_list);
+                                }
+                                Err(e) => error!("Error serializing the games list: {:?}", e),
+                            }
+                        }
+                    },
+                )
+                .await;
+
+            // Save the downloaded resources paths to the store
+            let global_local_game_list = LOCAL_GAME_LIST.read().await;
+            store.set(
+                env::STORE_LOCAL_GAME_LIST_KEY,
+                serde_json::to_value(&*global_local_game_list).unwrap(),
+            );
+            info!("Games resources downloaded.");
+        }
     });
 
     Ok(())
@@ -397,6 +510,10 @@ fn application_setup(app: &mut App) -> errors::Result<()> {
 pub fn quit_app(app: &AppHandle) {
     match app.store(env::STORE_FILE_NAME) {
         Ok(store) => {
+            // Flush the pending changes (e.g. the download queue) before closing
+            if let Err(e) = store.save() {
+                eprintln!("Error saving store: {:?}", e);
+            }
             store.close_resource();
         }
         Err(e) => {
@@ -0,0 +1,481 @@
+use crate::download::{DownloadSteps, GameDownload};
+use crate::env::{LOCAL_GAME_LIST, UPDATE_RATE};
+use crate::errors::Verror::{GameResourceIntegrityError, LibraryFolderError, NotEnoughDiskSpace};
+use crate::games::Game;
+use crate::hash::{copy_file_with_progress, sha256_file_with_progress};
+use crate::install_manifest::InstallManifest;
+use crate::manifest::list_files;
+use crate::{archive, cache, env, errors, install, paths, settings};
+use log::{error, info};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_fs::FsExt;
+
+/// # Library folders module
+/// Games are installed in library folders. The app data directory is the default library, and the
+/// player can register extra ones in the settings (e.g. on another drive or an external disk).
+/// Each game remembers the library it is installed in (`Game::install_location`), None standing for the default one.
+
+/// Return every library folder, starting with the default one.
+pub fn get_library_folders(app: &AppHandle) -> errors::Result<Vec<PathBuf>> {
+    let mut library_folders = vec![app.path().app_data_dir()?];
+    library_folders.extend(settings::load_settings(app).library_folders);
+    Ok(library_folders)
+}
+
+/// Return the install location matching a library folder chosen by the player, None for the default library.<br>
+/// The folder must be registered in the settings.
+pub fn get_install_location(app: &AppHandle, library: &Path) -> errors::Result<Option<PathBuf>> {
+    if library == app.path().app_data_dir()? {
+        return Ok(None);
+    }
+    if !settings::load_settings(app)
+        .library_folders
+        .iter()
+        .any(|library_folder| library_folder == library)
+    {
+        return Err(LibraryFolderError(format!(
+            "{}: the folder is not registered in the settings",
+            library.display()
+        )));
+    }
+    Ok(Some(library.to_path_buf()))
+}
+
+/// Allow the frontend to access the files of the library folders, through the fs plugin and the asset protocol.
+pub fn allow_library_folders(app: &AppHandle, library_folders: &[PathBuf]) -> errors::Result<()> {
+    for library_folder in library_folders {
+        app.fs_scope().allow_directory(library_folder, true)?;
+        app.asset_protocol_scope()
+            .allow_directory(library_folder, true)?;
+    }
+    Ok(())
+}
+
+/// Check and apply the library folders of new settings:
+/// - Each folder must be an absolute path, and is created if missing.
+/// - A folder can't be removed while a game is installed in it. The uninstalled games still located in it
+///   are moved back to the default library.
+pub async fn update_library_folders(
+    app: &AppHandle,
+    library_folders: &[PathBuf],
+) -> errors::Result<()> {
+    if let Some(library_folder) = library_folders
+        .iter()
+        .find(|library_folder| !library_folder.is_absolute())
+    {
+        return Err(LibraryFolderError(format!(
+            "{}: the path must be absolute",
+            library_folder.display()
+        )));
+    }
+
+    {
+        let mut game_list = LOCAL_GAME_LIST.write().await;
+        let removed_location = |game: &Game| {
+            game.install_location
+                .as_ref()
+                .filter(|install_location| !library_folders.contains(install_location))
+                .is_some()
+        };
+        if let Some(game) = game_list
+            .values()
+            .find(|game| removed_location(game) && game.game_archive.link.local_path.is_some())
+        {
+            return Err(LibraryFolderError(format!(
+                "{}: {} is installed in it, move or uninstall the game first",
+                game.install_location.as_ref().unwrap().display(),
+                game.title
+            )));
+        }
+        for game in game_list.values_mut() {
+            if removed_location(game) {
+                info!("Moving {} back to the default library", game.title);
+                game.install_location = None;
+            }
+        }
+    }
+
+    for library_folder in library_folders {
+        fs::create_dir_all(library_folder)?;
+    }
+    allow_library_folders(app, library_folders)
+}
+
+/// Move the install folder of a game to another folder, usually in another library.<br>
+/// Within a filesystem, the folder is renamed. Otherwise it is copied by `copy_install`, and the source is left
+/// in place, to be deleted by `remove_moved_install` once the new location of the game is saved.
+pub fn move_install(
+    game: &Game,
+    source: &Path,
+    destination: &Path,
+    resources_folder: &Path,
+    download: &mut GameDownload,
+) -> errors::Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Rename the folder, unless it also holds the resources of the game which stay in the app data directory
+    if source != resources_folder
+        && !destination.exists()
+        && fs::rename(source, destination).is_ok()
+    {
+        return Ok(());
+    }
+    copy_install(game, source, destination, resources_folder, download)
+}
+
+/// Copy the install folder of a game to another folder, leaving the source untouched.<br>
+/// Every file is copied to a staging folder next to the destination and verified against the hash of its source.
+/// The copy is then swapped in, keeping the files already in the destination (e.g. the resources of the game).
+pub fn copy_install(
+    game: &Game,
+    source: &Path,
+    destination: &Path,
+    resources_folder: &Path,
+    download: &mut GameDownload,
+) -> errors::Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // 1 - List the files to copy, checking the destination volume has room for them
+    let resources = [
+        game.background_image.name.as_str(),
+        game.navigation_icon.name.as_str(),
+    ];
+    let relative_paths: Vec<PathBuf> = list_files(source)?
+        .into_iter()
+        .filter(|relative_path| {
+            source != resources_folder
+                || !resources.contains(&relative_path.to_string_lossy().replace('\\', "/").as_str())
+        })
+        .collect();
+    let total_size = relative_paths
+        .iter()
+        .map(|relative_path| fs::symlink_metadata(source.join(relative_path)).map(|m| m.len()))
+        .sum::<io::Result<u64>>()?;
+    let available_space = fs4::available_space(destination.parent().unwrap_or(destination))?;
+    if total_size > available_space {
+        return Err(NotEnoughDiskSpace {
+            required: total_size,
+            available: available_space,
+        });
+    }
+
+    // 2 - Copy the files to a staging folder and swap it in
+    let staging_folder = install::prepare_staging_folder(destination)?;
+    if let Err(e) = copy_files(
+        source,
+        &staging_folder,
+        &relative_paths,
+        total_size,
+        download,
+    ) {
+        if let Err(e) = fs::remove_dir_all(&staging_folder) {
+            error!(
+                "Error removing the staging folder \"{}\": {:?}",
+                staging_folder.display(),
+                e
+            );
+        }
+        return Err(e);
+    }
+    let moved_files = install::carry_over_files(destination, &staging_folder, &[])?;
+    install::swap_install(&staging_folder, destination, false)
+        .inspect_err(|_| install::put_back_files(destination, &staging_folder, &moved_files))?;
+    Ok(())
+}
+
+/// Delete the source of an install copied by `move_install`. The resources of the game are kept.
+pub fn remove_moved_install(
+    game: &Game,
+    source: &Path,
+    resources_folder: &Path,
+) -> errors::Result<()> {
+    // The folder was renamed
+    if !source.exists() {
+        return Ok(());
+    }
+    install::remove_install(source, game, false)?;
+    if source != resources_folder {
+        fs::remove_dir(source)?;
+    }
+    Ok(())
+}
+
+/// Copy the files to the staging folder, then verify each copy against the hash of its source.
+fn copy_files(
+    source: &Path,
+    staging_folder: &Path,
+    relative_paths: &[PathBuf],
+    total_size: u64,
+    download: &mut GameDownload,
+) -> errors::Result<()> {
+    let mut last_update = Instant::now() - Duration::from_millis(UPDATE_RATE as u64);
+    let mut report_progress = |download: &mut GameDownload, processed: u64| {
+        download.update(processed, None);
+        if (last_update.elapsed().as_millis() as u16) >= UPDATE_RATE {
+            download.advertise();
+            last_update = Instant::now();
+        }
+    };
+
+    // 1 - Copy the files, keeping the hash of the data read
+    download.set_file_size(total_size);
+    download.set_start_time(Instant::now());
+    download.update(0, None);
+    download.set_steps(DownloadSteps::Moving);
+    let mut copied: u64 = 0;
+    let mut copied_files = Vec::with_capacity(relative_paths.len());
+    for relative_path in relative_paths {
+        let source_path = source.join(relative_path);
+        let target = staging_folder.join(relative_path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let metadata = fs::symlink_metadata(&source_path)?;
+        if metadata.file_type().is_symlink() {
+            archive::create_symlink(&fs::read_link(&source_path)?, &target)?;
+            copied += metadata.len();
+            continue;
+        }
+        let hash = copy_file_with_progress(&source_path, &target, &mut |read| {
+            copied += read;
+            report_progress(download, copied);
+        })?;
+        fs::set_permissions(&target, metadata.permissions())?;
+        copied_files.push((target, hash));
+    }
+    download.advertise();
+
+    // 2 - Read the copies back, so a faulty drive doesn't go unnoticed
+    download.set_start_time(Instant::now());
+    download.update(0, None);
+    download.set_steps(DownloadSteps::Verifying);
+    let mut verified: u64 = 0;
+    for (target, expected_hash) in copied_files {
+        let hash = sha256_file_with_progress(&target, &mut |read| {
+            verified += read;
+            report_progress(download, verified);
+        })?;
+        if hash != expected_hash {
+            return Err(GameResourceIntegrityError(target.display().to_string()));
+        }
+    }
+    download.advertise();
+    Ok(())
+}
+
+/// Move the folders named after the title of the games (by older launcher versions) to their folders named after their id.<br>
+/// It runs on startup, before the titles are updated from the catalog:
+/// - An install folder is renamed only if it belongs to the game. Titles without any letter or digit had their files
+///   installed straight in the library folder, only the files listed by their install manifest can be moved.
+///   The installs that can't be told apart are marked as not installed, to be imported again with `import_game`.
+/// - The resources, the installed content manifest and the cached archive are moved one by one, as games with colliding
+///   titles shared their folder. A folder is only shared by the installed games using it.
+pub fn migrate_legacy_folders(app: &AppHandle, game_list: &mut HashMap<u8, Game>) {
+    let app_data_dir = match app.path().app_data_dir() {
+        Ok(app_data_dir) => app_data_dir,
+        Err(e) => {
+            error!("Error getting the app data directory: {:?}", e);
+            return;
+        }
+    };
+    // Only the installed games have an install folder and a content manifest,
+    // the cached archives are kept for the uninstalled games too
+    let mut legacy_install_folders: HashMap<(Option<PathBuf>, String), usize> = HashMap::new();
+    let mut legacy_installed_names: HashMap<String, usize> = HashMap::new();
+    let mut legacy_folder_names: HashMap<String, usize> = HashMap::new();
+    for game in game_list.values() {
+        let legacy_folder_name = game.get_legacy_folder_name();
+        if game.game_archive.link.local_path.is_some() {
+            *legacy_install_folders
+                .entry((
+                    game.install_location.to_owned(),
+                    legacy_folder_name.to_owned(),
+                ))
+                .or_default() += 1;
+            *legacy_installed_names
+                .entry(legacy_folder_name.to_owned())
+                .or_default() += 1;
+        }
+        *legacy_folder_names.entry(legacy_folder_name).or_default() += 1;
+    }
+
+    for game in game_list.values_mut() {
+        let legacy_folder_name = game.get_legacy_folder_name();
+        let sharing = LegacySharing {
+            install: legacy_install_folders
+                .get(&(
+                    game.install_location.to_owned(),
+                    legacy_folder_name.to_owned(),
+                ))
+                .is_some_and(|count| *count > 1),
+            manifest: legacy_installed_names
+                .get(&legacy_folder_name)
+                .is_some_and(|count| *count > 1),
+            cached_archive: legacy_folder_names[&legacy_folder_name] > 1,
+        };
+        if let Err(e) = migrate_game_folders(&app_data_dir, game, &sharing) {
+            error!(
+                "Error moving the folders of {} to \"{}\": {:?}",
+                game.title,
+                game.get_folder_name(),
+                e
+            );
+        }
+    }
+}
+
+/// Whether the legacy files of a game are shared with other games whose titles collide with its own.
+struct LegacySharing {
+    install: bool,
+    manifest: bool,
+    cached_archive: bool,
+}
+
+fn migrate_game_folders(
+    app_data_dir: &Path,
+    game: &mut Game,
+    sharing: &LegacySharing,
+) -> errors::Result<()> {
+    let legacy_folder_name = game.get_legacy_folder_name();
+    let folder_name = game.get_folder_name();
+
+    // 1 - Move the install folder
+    let library_folder = game
+        .install_location
+        .to_owned()
+        .unwrap_or_else(|| app_data_dir.to_path_buf());
+    let legacy_install_folder = library_folder.join(&legacy_folder_name);
+    let install_folder = library_folder.join(&folder_name);
+    let is_legacy_install = game
+        .game_archive
+        .link
+        .local_path
+        .as_ref()
+        .is_some_and(|local_path| {
+            local_path.starts_with(&legacy_install_folder)
+                && !local_path.starts_with(&install_folder)
+        });
+    if is_legacy_install {
+        // The folder is already in place if the local game list wasn't saved after the previous migration
+        let is_moved = install_folder.exists()
+            || move_legacy_install(
+                game,
+                &legacy_install_folder,
+                &install_folder,
+                sharing.install,
+            )?;
+        let game_archive = &mut game.game_archive;
+        if is_moved {
+            let executable_path = game_archive.link.local_path.to_owned().unwrap();
+            let relative_path = executable_path
+                .strip_prefix(&legacy_install_folder)
+                .unwrap_or(&executable_path);
+            game_archive.link.local_path = Some(install_folder.join(relative_path));
+        } else {
+            error!(
+                "The install of {} can't be told apart from the other files of \"{}\", import it again",
+                game.title,
+                legacy_install_folder.display()
+            );
+            game_archive.link.local_path = None;
+            game_archive.installed_revision = None;
+            game_archive.need_update = false;
+            game.installed_version = None;
+        }
+    }
+
+    // 2 - Move the resources, already moved with the install folder in the default library
+    let legacy_resources_folder = app_data_dir.join(&legacy_folder_name);
+    let resources_folder = app_data_dir.join(&folder_name);
+    for link in [&mut game.background_image, &mut game.navigation_icon] {
+        let legacy_path = paths::join_inside(&legacy_resources_folder, &link.name)?;
+        if link.local_path.as_ref() != Some(&legacy_path) {
+            continue;
+        }
+        let path = paths::join_inside(&resources_folder, &link.name)?;
+        if legacy_path.is_file() && !path.exists() {
+            move_file(&legacy_path, &path)?;
+        }
+        // A resource found nowhere is downloaded again
+        link.local_path = path.is_file().then_some(path);
+    }
+
+    // 3 - Move the content manifest of the installed revision
+    let manifests_folder = app_data_dir.join(env::CONTENT_MANIFESTS_FOLDER);
+    let legacy_manifest_path = manifests_folder.join(format!("{}.json", legacy_folder_name));
+    let manifest_path = manifests_folder.join(format!("{}.json", folder_name));
+    if !sharing.manifest && legacy_manifest_path.is_file() && !manifest_path.exists() {
+        fs::rename(&legacy_manifest_path, &manifest_path)?;
+    }
+
+    // 4 - Move the cached archive of the latest revision
+    if !sharing.cached_archive {
+        cache::migrate_legacy_archive(app_data_dir, game)?;
+    }
+    Ok(())
+}
+
+/// Move the install folder named after the title of the game to its new folder.<br>
+/// Return false if the files of the game can't be told apart from the ones of other games.
+fn move_legacy_install(
+    game: &Game,
+    legacy_install_folder: &Path,
+    install_folder: &Path,
+    is_shared: bool,
+) -> errors::Result<bool> {
+    let install_manifest = InstallManifest::load(legacy_install_folder)
+        .filter(|install_manifest| install_manifest.game_id == game.id);
+    let is_library_folder = game.get_legacy_folder_name().is_empty();
+    info!(
+        "Moving the install of {} to \"{}\"",
+        game.title,
+        install_folder.display()
+    );
+
+    if !is_library_folder && (!is_shared || install_manifest.is_some()) {
+        // The whole folder belongs to the game
+        fs::rename(legacy_install_folder, install_folder)?;
+        let legacy_rollback_folder = install::get_rollback_folder(legacy_install_folder);
+        if legacy_rollback_folder.exists() {
+            fs::rename(
+                &legacy_rollback_folder,
+                install::get_rollback_folder(install_folder),
+            )?;
+        }
+        return Ok(true);
+    }
+
+    // The files are mixed with the ones of the library folder, only the ones of the install manifest are known
+    let Some(install_manifest) = install_manifest else {
+        return Ok(false);
+    };
+    for file in &install_manifest.files {
+        move_file(
+            &paths::join_inside(legacy_install_folder, &file.path)?,
+            &paths::join_inside(install_folder, &file.path)?,
+        )?;
+    }
+    move_file(
+        &legacy_install_folder.join(env::INSTALL_MANIFEST_FILE),
+        &install_folder.join(env::INSTALL_MANIFEST_FILE),
+    )?;
+    Ok(true)
+}
+
+/// Move a file, creating the folders of its new path.
+fn move_file(source: &Path, target: &Path) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(source, target)
+}
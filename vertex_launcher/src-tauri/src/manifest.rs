@@ -0,0 +1,414 @@
+use crate::download::{DownloadSteps, GameDownload};
+use crate::env::{LOCAL_GAME_LIST, UPDATE_RATE};
+use crate::errors::Verror::{GameResourceDownloadError, GameResourceIntegrityError};
+use crate::games::{Game, Link};
+use crate::hash::{sha256_bytes, sha256_file, verify_file};
+use crate::install_manifest::InstallManifest;
+use crate::{auth, env, errors, paths};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_http::reqwest::Response;
+
+/// # Content manifests module
+/// A content manifest is published by the catalog (`download_link.manifest_url`) and lists every file
+/// of the latest revision of a game. Each file is made of chunks identified by their hash:
+/// ```json5
+/// {
+///     "revision": 2,
+///     "chunk_base_url": "https://...", // Each chunk is downloaded from "<chunk_base_url>/<chunk sha256>"
+///     "files": [
+///         {
+///             "path": "bin/game.pak", // Relative path of the file in the install folder
+///             "size": 4194304,
+///             "sha256": "...",
+///             "chunks": [ { "sha256": "...", "size": 4194304 } ]
+///         }
+///     ]
+/// }
+/// ```
+/// Only the files that changed since the installed revision are rebuilt. Their chunks are read
+/// from any installed game that already has them, and downloaded only when no local copy exists.<br>
+/// The manifest of each install is kept in the `env::CONTENT_MANIFESTS_FOLDER` of the app data directory.
+
+/// The extension added to the rebuilt files until their hash is verified.
+const STAGING_EXTENSION: &str = "vertex_update";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentManifest {
+    pub revision: u64,
+    pub chunk_base_url: String,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+    pub chunks: Vec<ManifestChunk>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestChunk {
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// The location of a chunk inside an installed file.
+struct ChunkSource {
+    path: PathBuf,
+    offset: u64,
+    size: u64,
+}
+
+/// Bring the install folder of the game to the revision described by the manifest at `manifest_url`.<br>
+/// Files that changed are rebuilt, the files of the previous revision absent from the manifest are removed, and the
+/// manifest is saved as the installed one. Return the installed manifest.
+pub async fn install_from_manifest(
+    app: &AppHandle,
+    game: &Game,
+    manifest_url: &str,
+    install_folder: &Path,
+    download: &mut GameDownload,
+) -> errors::Result<ContentManifest> {
+    // 1 - Fetch the manifest of the latest revision
+    let response = fetch(app, game, manifest_url).await?;
+    let manifest: ContentManifest = serde_json::from_str(&response.text().await?)?;
+
+    // 2 - Find the files that changed since the installed revision
+    let previous_hashes: HashMap<String, String> = load_installed_manifest(app, game)
+        .map(|previous| {
+            previous
+                .files
+                .into_iter()
+                .map(|file| (file.path, file.sha256))
+                .collect()
+        })
+        .unwrap_or_default();
+    // The files preserved by the player are only written if they are missing
+    let outdated_files: Vec<&ManifestFile> = manifest
+        .files
+        .iter()
+        .filter(|file| !is_file_up_to_date(install_folder, file, &previous_hashes))
+        .filter(|file| {
+            !(game.is_path_preserved(&file.path) && install_folder.join(&file.path).exists())
+        })
+        .collect();
+    info!(
+        "{} of {} files of {} need to be updated",
+        outdated_files.len(),
+        manifest.files.len(),
+        game.title
+    );
+
+    // 3 - Rebuild each outdated file from chunks already on disk, or downloaded
+    rebuild_files(
+        app,
+        game,
+        &manifest,
+        &outdated_files,
+        install_folder,
+        download,
+    )
+    .await?;
+
+    // 4 - Remove the files of the previous revision that are not part of this one, except the preserved paths.
+    // The files unknown to the launcher (e.g. saves written next to the executable) are left alone.
+    download.set_steps(DownloadSteps::Cleaning);
+    let manifest_paths: HashSet<&str> = manifest
+        .files
+        .iter()
+        .map(|file| file.path.as_str())
+        .collect();
+    let mut previous_paths: HashSet<String> = previous_hashes.into_keys().collect();
+    if let Some(install_manifest) = InstallManifest::load_trusted(install_folder) {
+        previous_paths.extend(install_manifest.files.into_iter().map(|file| file.path));
+    }
+    for relative_path in previous_paths {
+        if manifest_paths.contains(relative_path.as_str()) || game.is_path_preserved(&relative_path)
+        {
+            continue;
+        }
+        let path = paths::join_inside_without_symlink(install_folder, &relative_path)?;
+        if fs::symlink_metadata(&path).is_ok() {
+            info!("Removing {} from {}", relative_path, game.title);
+            fs::remove_file(&path)?;
+        }
+    }
+
+    // 5 - Keep the manifest to know which files are installed at the next update
+    save_installed_manifest(app, game, &manifest)?;
+
+    Ok(manifest)
+}
+
+/// Rebuild the given files of the installed revision, e.g. after they were found missing or modified.
+pub async fn repair_files(
+    app: &AppHandle,
+    game: &Game,
+    install_folder: &Path,
+    broken_files: &HashSet<String>,
+    download: &mut GameDownload,
+) -> errors::Result<()> {
+    let manifest = load_installed_manifest(app, game).ok_or(GameResourceDownloadError(format!(
+        "no content manifest installed for {}",
+        game.title
+    )))?;
+    let files: Vec<&ManifestFile> = manifest
+        .files
+        .iter()
+        .filter(|file| broken_files.contains(&file.path))
+        .collect();
+    rebuild_files(app, game, &manifest, &files, install_folder, download).await
+}
+
+/// Rebuild each file from the chunks already on disk, or downloaded when no local copy exists.<br>
+/// Each file is written next to its target and only replaces it once verified.
+async fn rebuild_files(
+    app: &AppHandle,
+    game: &Game,
+    manifest: &ContentManifest,
+    files: &[&ManifestFile],
+    install_folder: &Path,
+    download: &mut GameDownload,
+) -> errors::Result<()> {
+    download.set_file_size(files.iter().map(|file| file.size).sum());
+    download.set_steps(DownloadSteps::Downloading);
+
+    let mut chunk_index = build_chunk_index(app).await;
+    let mut downloaded: u64 = 0;
+    let mut last_update = Instant::now() - Duration::from_millis(UPDATE_RATE as u64);
+    for file in files {
+        let target = paths::join_inside_without_symlink(install_folder, &file.path)?;
+        let staging = get_staging_path(&target);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut output = File::create(&staging)?;
+        let mut written_chunks: Vec<(String, ChunkSource)> = Vec::new();
+        let mut offset: u64 = 0;
+        for chunk in &file.chunks {
+            let data = match chunk_index
+                .get(&chunk.sha256)
+                .and_then(|source| read_chunk(source, chunk))
+            {
+                Some(data) => data,
+                None => {
+                    let data = download_chunk(app, game, &manifest.chunk_base_url, chunk).await?;
+                    // The next occurrences of the chunk in this update are read from the staging file
+                    chunk_index.insert(
+                        chunk.sha256.to_owned(),
+                        ChunkSource {
+                            path: staging.to_owned(),
+                            offset,
+                            size: chunk.size,
+                        },
+                    );
+                    data
+                }
+            };
+            output.write_all(&data)?;
+            written_chunks.push((
+                chunk.sha256.to_owned(),
+                ChunkSource {
+                    path: target.to_owned(),
+                    offset,
+                    size: chunk.size,
+                },
+            ));
+            offset += chunk.size;
+
+            downloaded += chunk.size;
+            download.update(downloaded, None);
+            if (last_update.elapsed().as_millis() as u16) >= UPDATE_RATE {
+                download.advertise();
+                last_update = Instant::now();
+            }
+        }
+        output.flush()?;
+        drop(output);
+
+        if let Err(e) = verify_file(&staging, &file.sha256) {
+            let _ = fs::remove_file(&staging);
+            return Err(e);
+        }
+        fs::rename(&staging, &target)?;
+
+        // The chunks of this file are now read from its verified version
+        chunk_index.extend(written_chunks);
+    }
+    // advertise a last time to get the 100% of progress
+    download.advertise();
+
+    Ok(())
+}
+
+/// Return the manifest of the revision installed for this game, if it was installed from a manifest.
+pub fn load_installed_manifest(app: &AppHandle, game: &Game) -> Option<ContentManifest> {
+    let manifest_path = get_installed_manifest_path(app, game).ok()?;
+    serde_json::from_slice(&fs::read(manifest_path).ok()?).ok()
+}
+
+/// Forget the manifest of the revision installed for this game, once uninstalled.
+pub fn remove_installed_manifest(app: &AppHandle, game: &Game) -> errors::Result<()> {
+    let manifest_path = get_installed_manifest_path(app, game)?;
+    if manifest_path.exists() {
+        fs::remove_file(manifest_path)?;
+    }
+    Ok(())
+}
+
+fn save_installed_manifest(
+    app: &AppHandle,
+    game: &Game,
+    manifest: &ContentManifest,
+) -> errors::Result<()> {
+    let manifest_path = get_installed_manifest_path(app, game)?;
+    if let Some(parent) = manifest_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(manifest_path, serde_json::to_vec(manifest)?)?;
+    Ok(())
+}
+
+fn get_installed_manifest_path(app: &AppHandle, game: &Game) -> errors::Result<PathBuf> {
+    Ok(app
+        .path()
+        .app_data_dir()?
+        .join(env::CONTENT_MANIFESTS_FOLDER)
+        .join(format!("{}.json", game.get_folder_name())))
+}
+
+/// Check if the installed file already matches its manifest entry.<br>
+/// The hash of the installed revision is trusted when known, otherwise the file content is hashed.
+fn is_file_up_to_date(
+    install_folder: &Path,
+    file: &ManifestFile,
+    previous_hashes: &HashMap<String, String>,
+) -> bool {
+    let Ok(path) = paths::join_inside(install_folder, &file.path) else {
+        return false;
+    };
+    match fs::metadata(&path) {
+        Ok(metadata) if metadata.len() == file.size => {}
+        _ => return false,
+    }
+
+    match previous_hashes.get(&file.path) {
+        Some(previous_hash) => previous_hash.eq_ignore_ascii_case(&file.sha256),
+        None => sha256_file(&path).is_ok_and(|hash| hash.eq_ignore_ascii_case(&file.sha256)),
+    }
+}
+
+/// Index every chunk of the games installed from a manifest by its hash.
+async fn build_chunk_index(app: &AppHandle) -> HashMap<String, ChunkSource> {
+    let mut chunk_index = HashMap::new();
+    let game_list = LOCAL_GAME_LIST.read().await;
+    for game in game_list.values() {
+        let (Some(manifest), Ok(install_folder)) = (
+            load_installed_manifest(app, game),
+            game.get_install_folder(app),
+        ) else {
+            continue;
+        };
+
+        for file in manifest.files {
+            let Ok(path) = paths::join_inside(&install_folder, &file.path) else {
+                continue;
+            };
+            let mut offset: u64 = 0;
+            for chunk in file.chunks {
+                chunk_index.entry(chunk.sha256).or_insert(ChunkSource {
+                    path: path.to_owned(),
+                    offset,
+                    size: chunk.size,
+                });
+                offset += chunk.size;
+            }
+        }
+    }
+    chunk_index
+}
+
+/// Read a chunk from an installed file.<br>
+/// Return None if the file changed since and the chunk no longer matches its size or hash.
+fn read_chunk(source: &ChunkSource, chunk: &ManifestChunk) -> Option<Vec<u8>> {
+    // The sizes come from the catalog, check them before allocating the chunk
+    if source.size != chunk.size {
+        return None;
+    }
+    let mut file = File::open(&source.path).ok()?;
+    let file_size = file.metadata().ok()?.len();
+    if source.offset.checked_add(source.size)? > file_size {
+        return None;
+    }
+    file.seek(SeekFrom::Start(source.offset)).ok()?;
+    let mut data = vec![0; source.size as usize];
+    file.read_exact(&mut data).ok()?;
+
+    sha256_bytes(&data)
+        .eq_ignore_ascii_case(&chunk.sha256)
+        .then_some(data)
+}
+
+async fn download_chunk(
+    app: &AppHandle,
+    game: &Game,
+    chunk_base_url: &str,
+    chunk: &ManifestChunk,
+) -> errors::Result<Vec<u8>> {
+    let url = format!("{}/{}", chunk_base_url.trim_end_matches('/'), chunk.sha256);
+    let response = fetch(app, game, &url).await?;
+
+    let data = response.bytes().await?.to_vec();
+    if data.len() as u64 != chunk.size || !sha256_bytes(&data).eq_ignore_ascii_case(&chunk.sha256) {
+        return Err(GameResourceIntegrityError(url));
+    }
+    Ok(data)
+}
+
+/// Send a request for a manifest or a chunk, with the credentials required by the link of the game archive.
+async fn fetch(app: &AppHandle, game: &Game, url: &str) -> errors::Result<Response> {
+    let link = Link {
+        url: url.to_string(),
+        ..game.game_archive.link.to_owned()
+    };
+    let response = auth::authorize_request(app, &link).await?.send().await?;
+    auth::check_response(&response)?;
+    Ok(response)
+}
+
+/// List every file of a folder and its sub-folders, relative to the folder.
+pub fn list_files(folder: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut folders = vec![PathBuf::new()];
+    while let Some(relative_folder) = folders.pop() {
+        for entry in fs::read_dir(folder.join(&relative_folder))? {
+            let entry = entry?;
+            let relative_path = relative_folder.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                folders.push(relative_path);
+            } else {
+                files.push(relative_path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Return the path where the new version of `target` is written until it is verified.
+fn get_staging_path(target: &Path) -> PathBuf {
+    let mut staging = target.as_os_str().to_owned();
+    staging.push(".");
+    staging.push(STAGING_EXTENSION);
+    PathBuf::from(staging)
+}
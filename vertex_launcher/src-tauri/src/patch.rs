@@ -0,0 +1,260 @@
+use crate::download::{download_file, DownloadSteps, GameDownload};
+use crate::errors::Verror::GamePatchError;
+use crate::games::{ArchivePatch, Game};
+use crate::hash::verify_file;
+use crate::{auth, errors, install, paths};
+use log::{error, info};
+use serde::Deserialize;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_http::reqwest::header::ACCEPT;
+
+/// # Delta patches module
+/// A delta patch is a zip archive published by the catalog to update an install from one revision to the next.<br>
+/// It contains a `patch.json` file describing the actions to perform on the installed files:
+/// ```json5
+/// {
+///     "files": [
+///         // Rebuild the file from its installed version and a zstd delta (`zstd --patch-from=old new`)
+///         { "action": "patch", "path": "bin/game.pak", "data": "deltas/game.pak.zst", "sha256": "..." },
+///         // Add or replace the file with the entry stored in the patch
+///         { "action": "add", "path": "bin/new.dll", "data": "files/new.dll", "sha256": "..." },
+///         // Remove the file from the install
+///         { "action": "delete", "path": "bin/old.dll" }
+///     ]
+/// }
+/// ```
+/// Every rebuilt file is verified against its hash before any installed file is replaced.
+
+/// The name of the file describing the patch content inside a patch archive.
+const PATCH_DESCRIPTION_FILE: &str = "patch.json";
+
+/// The extension added to the rebuilt files until the whole patch is verified.
+const STAGING_EXTENSION: &str = "vertex_patch";
+
+/// The maximum back-reference distance (as a power of 2) accepted in a delta.<br>
+/// Deltas are generated with `--long`, so the decoder must accept windows as large as the reference file.
+const DELTA_WINDOW_LOG_MAX: u32 = 31;
+
+/// The size of the largest installed file a delta is applied to.<br>
+/// The decoder holds the whole reference file in memory, so bigger files fail the patch
+/// and the update falls back to a full download.
+const MAX_REFERENCE_SIZE: u64 = 1 << 30;
+
+#[derive(Debug, Deserialize)]
+struct PatchDescription {
+    files: Vec<PatchAction>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum PatchAction {
+    Patch {
+        path: String,
+        data: String,
+        sha256: String,
+    },
+    Add {
+        path: String,
+        data: String,
+        sha256: String,
+    },
+    Delete {
+        path: String,
+    },
+}
+
+impl PatchAction {
+    /// Return the path of the installed file targeted by the action.
+    fn get_path(&self) -> &str {
+        match self {
+            PatchAction::Patch { path, .. }
+            | PatchAction::Add { path, .. }
+            | PatchAction::Delete { path } => path,
+        }
+    }
+}
+
+/// A file changed by a patch.
+pub struct PatchedFile {
+    /// Path of the file relative to the install folder.
+    pub path: String,
+    /// The hash of the new version of the file, None if the file was deleted.
+    pub sha256: Option<String>,
+}
+
+/// Download and apply, in order, every delta of the chain on the install folder.<br>
+/// The patch archives are downloaded to the staging folder of the install, deleted once the chain is applied
+/// or rejected. The paths preserved by the player are left as they are.
+/// Return the files changed by the chain, in the order they were changed.
+pub async fn apply_patch_chain(
+    app: &AppHandle,
+    game: &Game,
+    patches: &[ArchivePatch],
+    install_folder: &Path,
+    download: &mut GameDownload,
+) -> errors::Result<Vec<PatchedFile>> {
+    let staging_folder = install::prepare_staging_folder(install_folder)?;
+    let result = download_and_apply(
+        app,
+        game,
+        patches,
+        install_folder,
+        &staging_folder,
+        download,
+    )
+    .await;
+
+    // The patch archives are useless once applied or rejected
+    if let Err(e) = fs::remove_dir_all(&staging_folder) {
+        error!(
+            "Error deleting the patches in \"{}\" : {:?}",
+            staging_folder.display(),
+            e
+        );
+    }
+    result
+}
+
+async fn download_and_apply(
+    app: &AppHandle,
+    game: &Game,
+    patches: &[ArchivePatch],
+    install_folder: &Path,
+    staging_folder: &Path,
+    download: &mut GameDownload,
+) -> errors::Result<Vec<PatchedFile>> {
+    let mut patched_files = Vec::new();
+    for archive_patch in patches {
+        info!(
+            "Applying delta from revision {} to {}",
+            archive_patch.from_revision, archive_patch.link.revision
+        );
+        let patch_path = paths::join_inside(staging_folder, &archive_patch.link.name)?;
+        let request = auth::authorize_request(app, &archive_patch.link)
+            .await?
+            .header(ACCEPT, "application/octet-stream");
+        download_file(request, &patch_path, download).await?;
+
+        download.set_steps(DownloadSteps::Patching);
+        patched_files.extend(apply_patch(&patch_path, game, install_folder)?);
+        fs::remove_file(&patch_path)?;
+    }
+
+    Ok(patched_files)
+}
+
+/// Apply one patch archive on the install folder.<br>
+/// The new files are first written next to the installed ones and verified,
+/// then swapped in once the whole patch is known to be valid.
+fn apply_patch(
+    patch_path: &Path,
+    game: &Game,
+    install_folder: &Path,
+) -> errors::Result<Vec<PatchedFile>> {
+    let mut archive = zip::ZipArchive::new(File::open(patch_path)?)?;
+    let mut description: PatchDescription =
+        serde_json::from_reader(archive.by_name(PATCH_DESCRIPTION_FILE)?)?;
+    // The files preserved by the player are only written by the patch if they are missing
+    description.files.retain(|action| {
+        let path = action.get_path();
+        !(game.is_path_preserved(path) && install_folder.join(path).exists())
+    });
+
+    let mut staged_files: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let result = (|| -> errors::Result<()> {
+        for action in &description.files {
+            match action {
+                PatchAction::Patch { path, data, sha256 } => {
+                    let target = paths::join_inside_without_symlink(install_folder, path)?;
+                    let staging = get_staging_path(&target);
+                    let reference_size = fs::metadata(&target)
+                        .map_err(|e| {
+                            GamePatchError(format!("the file {} can't be read: {}", path, e))
+                        })?
+                        .len();
+                    if reference_size > MAX_REFERENCE_SIZE {
+                        return Err(GamePatchError(format!(
+                            "the file {} is too large to be patched ({} bytes)",
+                            path, reference_size
+                        )));
+                    }
+                    let reference = fs::read(&target).map_err(|e| {
+                        GamePatchError(format!("the file {} can't be read: {}", path, e))
+                    })?;
+
+                    let mut decoder = zstd::stream::read::Decoder::with_ref_prefix(
+                        BufReader::new(archive.by_name(data)?),
+                        &reference,
+                    )?;
+                    decoder.window_log_max(DELTA_WINDOW_LOG_MAX)?;
+                    io::copy(&mut decoder, &mut File::create(&staging)?)?;
+                    staged_files.push((staging.clone(), target));
+
+                    verify_file(&staging, sha256)?;
+                }
+                PatchAction::Add { path, data, sha256 } => {
+                    let target = paths::join_inside_without_symlink(install_folder, path)?;
+                    let staging = get_staging_path(&target);
+                    if let Some(parent) = staging.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+
+                    io::copy(&mut archive.by_name(data)?, &mut File::create(&staging)?)?;
+                    staged_files.push((staging.clone(), target));
+
+                    verify_file(&staging, sha256)?;
+                }
+                PatchAction::Delete { .. } => {}
+            }
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        // Leave the install untouched if any file of the patch is invalid
+        for (staging, _) in &staged_files {
+            let _ = fs::remove_file(staging);
+        }
+        return Err(e);
+    }
+
+    // Every file has been verified, swap them in
+    for (staging, target) in &staged_files {
+        fs::rename(staging, target)?;
+    }
+    for action in &description.files {
+        if let PatchAction::Delete { path } = action {
+            let target = paths::join_inside_without_symlink(install_folder, path)?;
+            if target.exists() {
+                fs::remove_file(&target)?;
+            }
+        }
+    }
+
+    Ok(description
+        .files
+        .into_iter()
+        .map(|action| match action {
+            PatchAction::Patch { path, sha256, .. } | PatchAction::Add { path, sha256, .. } => {
+                PatchedFile {
+                    path,
+                    sha256: Some(sha256),
+                }
+            }
+            PatchAction::Delete { path } => PatchedFile { path, sha256: None },
+        })
+        .collect())
+}
+
+/// Return the path where the new version of `target` is written until the patch is verified.
+fn get_staging_path(target: &Path) -> PathBuf {
+    let mut staging = target.as_os_str().to_owned();
+    staging.push(".");
+    staging.push(STAGING_EXTENSION);
+    PathBuf::from(staging)
+}
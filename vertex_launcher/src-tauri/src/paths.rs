@@ -0,0 +1,103 @@
+use crate::errors;
+use crate::errors::Verror::UnsafePathError;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// # Paths module
+/// The catalog and the archives are not trusted: a file name like "../../.bashrc" must never be
+/// written or executed outside the game folder. Every path they supply is joined with `join_inside`.
+/// The archives can also contain symbolic links: their targets must not leave the folder of the link,
+/// and no file is written through a link already extracted (`join_inside_without_symlink`).
+
+/// Join a relative path supplied by the catalog or an archive to the folder.<br>
+/// The path is normalised ("." and ".." are resolved) and rejected if it is absolute or leads outside the folder.
+pub fn join_inside(folder: &Path, relative_path: impl AsRef<Path>) -> errors::Result<PathBuf> {
+    let relative_path = relative_path.as_ref();
+    let mut normalized_path = PathBuf::new();
+    for component in relative_path.components() {
+        match component {
+            Component::Normal(name) => normalized_path.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized_path.pop() {
+                    return Err(UnsafePathError(relative_path.display().to_string()));
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(UnsafePathError(relative_path.display().to_string()))
+            }
+        }
+    }
+    Ok(folder.join(normalized_path))
+}
+
+/// Join a relative path where a file is written or deleted to the folder, like `join_inside`.<br>
+/// The path is also rejected if one of its parent folders is a symbolic link, since a file written
+/// through it could end up anywhere.
+pub fn join_inside_without_symlink(
+    folder: &Path,
+    relative_path: impl AsRef<Path>,
+) -> errors::Result<PathBuf> {
+    let relative_path = relative_path.as_ref();
+    let path = join_inside(folder, relative_path)?;
+    let mut parent = path.parent();
+    while let Some(folder_to_check) =
+        parent.filter(|parent| parent.starts_with(folder) && *parent != folder)
+    {
+        if fs::symlink_metadata(folder_to_check)
+            .is_ok_and(|metadata| metadata.file_type().is_symlink())
+        {
+            return Err(UnsafePathError(relative_path.display().to_string()));
+        }
+        parent = folder_to_check.parent();
+    }
+    Ok(path)
+}
+
+/// Reject the target of a symbolic link supplied by an archive, unless it is a relative path
+/// that never goes up a folder.
+pub fn check_link_target(link_target: &Path) -> errors::Result<()> {
+    if link_target
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        Ok(())
+    } else {
+        Err(UnsafePathError(link_target.display().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_inside_resolves_relative_paths() {
+        let folder = Path::new("/games/vertex");
+        assert_eq!(
+            join_inside(folder, "bin/game.exe").unwrap(),
+            folder.join("bin/game.exe")
+        );
+        assert_eq!(
+            join_inside(folder, "./bin/../data/./level.pak").unwrap(),
+            folder.join("data/level.pak")
+        );
+    }
+
+    #[test]
+    fn join_inside_rejects_paths_leaving_the_folder() {
+        let folder = Path::new("/games/vertex");
+        assert!(join_inside(folder, "../.bashrc").is_err());
+        assert!(join_inside(folder, "bin/../../.bashrc").is_err());
+        assert!(join_inside(folder, "/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn check_link_target_only_accepts_descending_paths() {
+        assert!(check_link_target(Path::new("lib/libgame.so")).is_ok());
+        assert!(check_link_target(Path::new("./libgame.so")).is_ok());
+        assert!(check_link_target(Path::new("../libgame.so")).is_err());
+        assert!(check_link_target(Path::new("lib/../libgame.so")).is_err());
+        assert!(check_link_target(Path::new("/usr/lib/libgame.so")).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use crate::env::DOWNLOAD_QUEUE;
+use crate::{commands, env, settings};
+use log::{error, info};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_store::StoreExt;
+
+/// # Download queue module
+/// Every download requested is added to the queue until it ends. Downloads run one after another,
+/// and the queue is saved in the store each time it changes so that downloads interrupted by
+/// quitting the launcher can be resumed on the next startup.
+
+/// Add a game at the end of the queue, if it is not already in it.<br>
+/// Return false if the game was already queued.
+pub fn enqueue(app: &AppHandle, game_id: u8) -> bool {
+    let mut queue = DOWNLOAD_QUEUE.lock().unwrap();
+    if queue.contains(&game_id) {
+        return false;
+    }
+    queue.push(game_id);
+    persist(app, &queue);
+    true
+}
+
+/// Remove a game from the queue once its download ended, successfully or not.
+pub fn dequeue(app: &AppHandle, game_id: u8) {
+    let mut queue = DOWNLOAD_QUEUE.lock().unwrap();
+    queue.retain(|queued_game_id| *queued_game_id != game_id);
+    persist(app, &queue);
+}
+
+/// Return true if the game is being downloaded or waiting to be.
+pub fn is_queued(game_id: u8) -> bool {
+    DOWNLOAD_QUEUE.lock().unwrap().contains(&game_id)
+}
+
+/// Save the queue to the store and share it with the frontend.
+fn persist(app: &AppHandle, queue: &[u8]) {
+    match app.store(env::STORE_FILE_NAME) {
+        Ok(store) => {
+            store.set(env::STORE_DOWNLOAD_QUEUE_KEY, serde_json::json!(queue));
+            // Save right away, the launcher can be closed at any time during a download
+            if let Err(e) = store.save() {
+                error!("Error saving the download queue: {:?}", e);
+            }
+        }
+        Err(e) => error!(
+            "Error opening the store to save the download queue: {:?}",
+            e
+        ),
+    }
+
+    if let Err(e) = app.emit(env::EVENT_DOWNLOAD_QUEUE_UPDATED, queue) {
+        error!("Error emitting the download queue: {:?}", e);
+    }
+}
+
+/// Restart the downloads saved in the store by the previous session, in their original order.<br>
+/// If the player disabled it in the settings, the saved downloads are forgotten instead.
+pub fn resume_downloads(app: &AppHandle) {
+    let saved_queue: Vec<u8> = match app.store(env::STORE_FILE_NAME) {
+        Ok(store) => store
+            .get(env::STORE_DOWNLOAD_QUEUE_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default(),
+        Err(e) => {
+            error!("Error opening the store to resume the downloads: {:?}", e);
+            return;
+        }
+    };
+    if saved_queue.is_empty() {
+        return;
+    }
+
+    if !settings::load_settings(app).resume_downloads {
+        info!("Forgetting {} interrupted download(s)", saved_queue.len());
+        persist(app, &[]);
+        return;
+    }
+
+    info!("Resuming {} interrupted download(s)", saved_queue.len());
+    // Queue them all first so the frontend knows about every pending download
+    for game_id in &saved_queue {
+        enqueue(app, *game_id);
+    }
+
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        for game_id in saved_queue {
+            if let Err(e) = commands::run_queued_download(&app_handle, game_id).await {
+                error!("Error resuming the download of game {}: {:?}", game_id, e);
+            }
+        }
+    });
+}
@@ -0,0 +1,274 @@
+use crate::download::{download_file, DownloadSteps, GameDownload};
+use crate::env::UPDATE_RATE;
+use crate::errors::Verror::{GameRepairError, GameVerificationError};
+use crate::games::Game;
+use crate::hash::{sha256_file_with_progress, verify_file};
+use crate::install_manifest::{InstallManifest, InstalledFile};
+use crate::manifest::list_files;
+use crate::{archive, auth, cache, env, errors, install, manifest, paths};
+use log::{error, info};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tauri_plugin_http::reqwest::header::ACCEPT;
+
+/// # Verification and repair module
+/// An install is verified against the files recorded in its install manifest (or in its content manifest
+/// for the installs made before install manifests existed). Only the missing and modified files are
+/// fetched again by a repair: from the chunks of the content manifest, or from the game archive.
+
+/// The result of the verification of an install. Paths are relative to the install folder.
+#[derive(Debug, Default, Serialize)]
+pub struct VerificationReport {
+    /// Files of the game absent from the install folder.
+    pub missing: Vec<String>,
+    /// Files of the game whose size or content changed.
+    pub modified: Vec<String>,
+    /// Files of the install folder that are not part of the game, such as saves. They are never touched.
+    pub extra: Vec<String>,
+    /// Files whose size or content changed, but that can belong to the player: they are only known from
+    /// a scanned install manifest. They are never repaired.
+    pub unverifiable: Vec<String>,
+}
+
+impl VerificationReport {
+    /// Return the files to fetch again to repair the install.
+    pub fn get_broken_files(&self) -> HashSet<String> {
+        self.missing
+            .iter()
+            .chain(self.modified.iter())
+            .cloned()
+            .collect()
+    }
+}
+
+/// Return the files expected in the install folder of the game.<br>
+/// A scanned install manifest can list files created by the player, it is only used without a content manifest,
+/// and its changed files are reported as unverifiable.
+pub fn get_expected_files(
+    app: &AppHandle,
+    game: &Game,
+    install_folder: &Path,
+) -> errors::Result<InstallManifest> {
+    if let Some(install_manifest) = InstallManifest::load_trusted(install_folder) {
+        return Ok(install_manifest);
+    }
+    manifest::load_installed_manifest(app, game)
+        .map(|content_manifest| InstallManifest::from_content_manifest(game.id, &content_manifest))
+        .or_else(|| InstallManifest::load(install_folder))
+        .ok_or(GameVerificationError(format!(
+            "{}: the installed files are unknown, reinstall the game to be able to verify it",
+            game.title
+        )))
+}
+
+/// Check every expected file of the install against its size and hash.<br>
+/// The bytes verified are reported through `download`.
+pub fn verify_install(
+    install_folder: &Path,
+    game: &Game,
+    expected_files: &InstallManifest,
+    download: &mut GameDownload,
+) -> errors::Result<VerificationReport> {
+    let mut report = VerificationReport::default();
+    // The content of the paths preserved by the player belongs to them
+    let game_files: Vec<&InstalledFile> = expected_files
+        .files
+        .iter()
+        .filter(|file| !game.is_path_preserved(&file.path))
+        .collect();
+    download.set_file_size(game_files.iter().map(|file| file.size).sum());
+    download.set_start_time(Instant::now());
+    download.update(0, None);
+    download.set_steps(DownloadSteps::Verifying);
+
+    // 1 - Check the files of the game
+    let mut verified: u64 = 0;
+    let mut last_update = Instant::now() - Duration::from_millis(UPDATE_RATE as u64);
+    let changed_files = if expected_files.scanned {
+        &mut report.unverifiable
+    } else {
+        &mut report.modified
+    };
+    for file in game_files {
+        let path = paths::join_inside(install_folder, &file.path)?;
+        match fs::metadata(&path) {
+            Err(_) => report.missing.push(file.path.to_owned()),
+            Ok(metadata) if metadata.len() != file.size => changed_files.push(file.path.to_owned()),
+            Ok(_) => {
+                let hash = sha256_file_with_progress(&path, &mut |read| {
+                    verified += read;
+                    download.update(verified, None);
+                    if (last_update.elapsed().as_millis() as u16) >= UPDATE_RATE {
+                        download.advertise();
+                        last_update = Instant::now();
+                    }
+                })?;
+                if !hash.eq_ignore_ascii_case(&file.sha256) {
+                    changed_files.push(file.path.to_owned());
+                }
+                continue;
+            }
+        }
+        // The files that can't be hashed are verified right away
+        verified += file.size;
+        download.update(verified, None);
+    }
+    // advertise a last time to get the 100% of progress
+    download.advertise();
+
+    // 2 - Find the files that are not part of the game
+    let expected_paths: HashSet<&str> = expected_files
+        .files
+        .iter()
+        .map(|file| file.path.as_str())
+        .collect();
+    let launcher_files = [
+        env::INSTALL_MANIFEST_FILE,
+        game.background_image.name.as_str(),
+        game.navigation_icon.name.as_str(),
+    ];
+    for relative_path in list_files(install_folder)? {
+        let relative_path = relative_path.to_string_lossy().replace('\\', "/");
+        if !expected_paths.contains(relative_path.as_str())
+            && !launcher_files.contains(&relative_path.as_str())
+        {
+            report.extra.push(relative_path);
+        }
+    }
+
+    info!(
+        "{} verified: {} missing, {} modified, {} unverifiable and {} extra files",
+        game.title,
+        report.missing.len(),
+        report.modified.len(),
+        report.unverifiable.len(),
+        report.extra.len()
+    );
+    Ok(report)
+}
+
+/// Fetch again the missing and modified files of the report, and check them against their expected hash.<br>
+/// Archives are only published for the latest revision, so an outdated install must be updated instead.
+pub async fn repair_install(
+    app: &AppHandle,
+    game: &Game,
+    install_folder: &Path,
+    expected_files: &InstallManifest,
+    report: &VerificationReport,
+    download: &mut GameDownload,
+) -> errors::Result<()> {
+    let broken_files = report.get_broken_files();
+    if broken_files.is_empty() {
+        return Ok(());
+    }
+    info!("Repairing {} files of {}", broken_files.len(), game.title);
+
+    // Games installed at a past version of the catalog are repaired from the archive of that version
+    let game = &match game.get_pinned_version() {
+        Some(version) if game.game_archive.installed_revision == Some(version.link.revision) => {
+            Game {
+                game_archive: game.game_archive.get_version_archive(version),
+                ..game.to_owned()
+            }
+        }
+        _ => game.to_owned(),
+    };
+
+    // 1 - Games published with a content manifest rebuild their files from its chunks
+    let game_archive = &game.game_archive;
+    if game_archive.manifest_url.is_some() {
+        return manifest::repair_files(app, game, install_folder, &broken_files, download).await;
+    }
+    if game_archive.installed_revision != Some(game_archive.link.revision) {
+        return Err(GameRepairError(format!(
+            "{}: a newer revision has been published, update the game instead",
+            game.title
+        )));
+    }
+
+    // 2 - Get the archive from the cache, or download it to a staging folder
+    let staging_folder = install::prepare_staging_folder(install_folder)?;
+    let staged_archive_path = paths::join_inside(&staging_folder, &game_archive.link.name)?;
+    let mut cached_archive = if game_archive.need_extract {
+        cache::get_cached_archive(app, game)
+    } else {
+        None
+    };
+    loop {
+        let archive_path = match cached_archive.take() {
+            Some(cached_archive) => cached_archive,
+            None => {
+                let request = auth::authorize_request(app, &game_archive.link)
+                    .await?
+                    .header(ACCEPT, "application/octet-stream");
+                download_file(request, &staged_archive_path, download).await?;
+                staged_archive_path.to_owned()
+            }
+        };
+
+        // 3 - Extract only the broken files next to the install.
+        // A cached archive that can't be extracted is evicted and the archive is downloaded again
+        if game_archive.need_extract {
+            download.set_steps(DownloadSteps::Extracting);
+            let broken_size = expected_files
+                .files
+                .iter()
+                .filter(|file| broken_files.contains(&file.path))
+                .map(|file| file.size)
+                .sum();
+            if let Err(e) = archive::extract(
+                &archive_path,
+                game_archive.get_archive_format()?,
+                &staging_folder,
+                game_archive.strip_top_level_folder,
+                Some(&broken_files),
+                Some(broken_size),
+                download,
+            ) {
+                if archive_path == staged_archive_path {
+                    return Err(e);
+                }
+                error!(
+                    "Error extracting the cached archive of {}, downloading it again: {}",
+                    game.title, e
+                );
+                cache::evict_archive(&archive_path);
+                install::prepare_staging_folder(install_folder)?;
+                continue;
+            }
+            if archive_path == staged_archive_path {
+                cache::store_archive(app, game, &staged_archive_path)?;
+            }
+        }
+        break;
+    }
+
+    // 4 - Replace the broken files once each of them is verified
+    download.set_steps(DownloadSteps::Cleaning);
+    for file in expected_files
+        .files
+        .iter()
+        .filter(|file| broken_files.contains(&file.path))
+    {
+        let staged_file = paths::join_inside(&staging_folder, &file.path)?;
+        // A missing file recorded by a scanned install manifest can be a file of the player
+        if !staged_file.is_file() {
+            info!("Skipping {}, it is not part of the archive", file.path);
+            continue;
+        }
+        verify_file(&staged_file, &file.sha256)?;
+
+        let target = paths::join_inside_without_symlink(install_folder, &file.path)?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&staged_file, &target)?;
+    }
+    fs::remove_dir_all(&staging_folder)?;
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use crate::errors::Verror::StoreAccessError;
+use crate::{env, errors};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+/// The launcher settings editable by the player, saved in the store under `env::STORE_SETTINGS_KEY`.<br>
+/// Every field has a default value, so settings saved by an older launcher version stay valid.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub http: HttpSettings,
+    /// Resume the downloads interrupted by closing the launcher at the next startup.
+    pub resume_downloads: bool,
+    pub archive_cache: ArchiveCacheSettings,
+    /// Absolute paths of the folders games can be installed in, in addition to the app data directory.
+    pub library_folders: Vec<PathBuf>,
+    /// What to do when an update is found for a game without its own policy.
+    pub update_policy: UpdatePolicy,
+    /// Keep the previous revision of a game when it is updated, to roll back to it if the update breaks the game.
+    /// It takes as much disk space as the install itself.
+    pub keep_previous_version: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            http: HttpSettings::default(),
+            resume_downloads: true,
+            archive_cache: ArchiveCacheSettings::default(),
+            library_folders: Vec::new(),
+            update_policy: UpdatePolicy::default(),
+            keep_previous_version: false,
+        }
+    }
+}
+
+/// What to do when the catalog fetched on startup has an update for an installed game.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdatePolicy {
+    /// Queue the update right away.
+    Automatic,
+    /// Show a notification, the player starts the update.
+    #[default]
+    Notify,
+    /// Do nothing, the player checks the updates by themselves.
+    Manual,
+}
+
+/// Configuration of the cache keeping the downloaded archives after their extraction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveCacheSettings {
+    /// Keep the archives to reinstall or repair games without downloading them again.
+    pub keep_archives: bool,
+    /// Maximum size (in bytes) of the cache. The least recently used archives are deleted beyond it.
+    pub max_size: u64,
+}
+
+impl Default for ArchiveCacheSettings {
+    fn default() -> Self {
+        Self {
+            keep_archives: false,
+            max_size: env::ARCHIVE_CACHE_DEFAULT_MAX_SIZE,
+        }
+    }
+}
+
+/// Configuration of the HTTP client shared by every network path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    /// Url of the proxy used for every request (e.g. "http://proxy.local:3128"). None to connect directly.
+    pub proxy: Option<String>,
+    /// Maximum time (in seconds) to establish a connection.
+    pub connect_timeout: u64,
+    /// Maximum time (in seconds) without receiving any data before a request is aborted.
+    pub read_timeout: u64,
+    /// User agent sent with every request.
+    pub user_agent: String,
+    /// Path to a PEM root certificate trusted in addition to the system ones.
+    pub root_certificate: Option<PathBuf>,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            connect_timeout: env::HTTP_DEFAULT_CONNECT_TIMEOUT,
+            read_timeout: env::HTTP_DEFAULT_READ_TIMEOUT,
+            user_agent: env::generate_default_user_agent(),
+            root_certificate: None,
+        }
+    }
+}
+
+/// Load the settings from the store.<br>
+/// Fall back to the default settings if they are missing or invalid.
+pub fn load_settings(app: &AppHandle) -> Settings {
+    let store = match app.store(env::STORE_FILE_NAME) {
+        Ok(store) => store,
+        Err(e) => {
+            error!("Error opening the store to load the settings: {:?}", e);
+            return Settings::default();
+        }
+    };
+
+    match store.get(env::STORE_SETTINGS_KEY) {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            error!("Error loading settings, using the default ones: {:?}", e);
+            Settings::default()
+        }),
+        None => Settings::default(),
+    }
+}
+
+/// Save the settings to the store.
+pub fn save_settings(app: &AppHandle, settings: &Settings) -> errors::Result<()> {
+    let store = app
+        .store(env::STORE_FILE_NAME)
+        .map_err(|e| StoreAccessError(e.to_string()))?;
+    store.set(env::STORE_SETTINGS_KEY, serde_json::to_value(settings)?);
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use crate::env::LOCAL_GAME_LIST;
+use crate::games::GameUpdate;
+use crate::settings::UpdatePolicy;
+use crate::{commands, env, queue, settings};
+use log::{error, info};
+use tauri::{AppHandle, Emitter};
+
+/// # Update policies module
+/// The catalog is fetched on startup, and the games list marks the installed games with a newer revision
+/// (`need_update`). What happens next depends on the update policy of each game, or on the one of the settings
+/// if the player didn't choose one for the game:
+/// - Automatic: the update is queued right away.
+/// - Notify: a notification is shown and the frontend receives the `env::EVENT_UPDATES_AVAILABLE` event.
+/// - Manual: nothing, the updates are listed by the get_updates command.
+
+/// Queue, notify or ignore the available updates according to the update policy of each game.
+pub async fn apply_update_policies(app: &AppHandle) {
+    let default_policy = settings::load_settings(app).update_policy;
+    let mut automatic_updates: Vec<GameUpdate> = Vec::new();
+    let mut notified_updates: Vec<GameUpdate> = Vec::new();
+    {
+        let game_list = LOCAL_GAME_LIST.read().await;
+        let mut games: Vec<_> = game_list.values().collect();
+        games.sort_by(|a, b| a.weight.cmp(&b.weight).reverse());
+        for game in games {
+            // Interrupted updates are already resumed by the download queue
+            let Some(update) = game.get_update().filter(|_| !queue::is_queued(game.id)) else {
+                continue;
+            };
+            match game.update_policy.unwrap_or(default_policy) {
+                UpdatePolicy::Automatic => automatic_updates.push(update),
+                UpdatePolicy::Notify => notified_updates.push(update),
+                UpdatePolicy::Manual => {}
+            }
+        }
+    }
+
+    // 1 - Notify the player of the updates to start by themselves
+    for update in &notified_updates {
+        if let Err(e) = notifica::notify(
+            "Vertex Launcher",
+            &env::generate_update_available_message(&update.title, &update.version),
+        ) {
+            error!("Error notifying the update of {}: {}", update.title, e);
+        }
+    }
+    if !notified_updates.is_empty() {
+        if let Err(e) = app.emit(env::EVENT_UPDATES_AVAILABLE, &notified_updates) {
+            error!("Error emitting the available updates: {:?}", e);
+        }
+    }
+
+    // 2 - Queue the automatic updates, they are run one after another.
+    // Queue them all first so the frontend knows about every pending update
+    automatic_updates.retain(|update| queue::enqueue(app, update.game_id));
+    if automatic_updates.is_empty() {
+        return;
+    }
+    info!("Queuing {} automatic update(s)", automatic_updates.len());
+
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        for update in automatic_updates {
+            if let Err(e) = commands::run_queued_download(&app_handle, update.game_id).await {
+                error!("Error updating {}: {:?}", update.title, e);
+            }
+        }
+    });
+}
@@ -16,6 +16,8 @@ function downloadProgress({gameId}: DownloadProgressProps) {
         remaining_time: "N/A",
         speed: "0 MB/s",
         steps: "Starting",
+        processed_entries: 0,
+        total_entries: null as number | null,
     });
     const {selectedGame} = useGame(); // Fetch the selected game from the context
     const [downloadingGameId, setDownloadingGameId] = useState<number | null>(null);
@@ -69,6 +71,12 @@ function downloadProgress({gameId}: DownloadProgressProps) {
                 <p>
                     <strong>Total size:</strong> {getFormatedBytes(downloadData.file_size)}
                 </p>
+                {downloadData.steps === 'Extracting' && (
+                    <p>
+                        <strong>Files:</strong> {downloadData.processed_entries}
+                        {downloadData.total_entries !== null && ` / ${downloadData.total_entries}`}
+                    </p>
+                )}
             </div>
             <h3>{downloadData.steps}</h3>
         </div>
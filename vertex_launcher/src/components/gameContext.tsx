@@ -16,6 +16,17 @@ export const GameProvider = ({children}: { children: ReactNode }) => {
     const [selectedGame, setSelectedGame] = useState<Game | null>(null);
     const [downloadingGames, setDownloadingGames] = useState<Set<number>>(new Set());
 
+    // Keep the downloading games in sync with the backend queue, including the downloads resumed at startup
+    useEffect(() => {
+        const unlisten = listen<number[]>("download_queue_updated", (event) => {
+            setDownloadingGames(new Set(event.payload));
+        });
+
+        return () => {
+            unlisten.then(fn => fn());
+        };
+    }, []);
+
     useEffect(() => {
     const unlistenCallbacks: Promise<() => void>[] = [];
 
@@ -8,4 +8,6 @@ interface DownloadPayload {
     remaining_time: string;
     speed: string;
     steps: string;
+    processed_entries: number;
+    total_entries: number | null;
 }	
\ No newline at end of file
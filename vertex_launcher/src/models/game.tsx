@@ -25,11 +25,39 @@ interface Game {
         need_extract: boolean;
         strip_top_level_folder: boolean;
         path_to_executable: string;
+        // True if a newer revision than the installed one is available
+        need_update: boolean;
+        installed_revision: number | null;
+        download_size: number | null;
+        installed_size: number | null;
+        // Past versions that can be installed with the install_version command
+        versions: {
+            version: string;
+            link: {
+                url: string;
+                name: string;
+                revision: number;
+            };
+            download_size: number | null;
+            installed_size: number | null;
+        }[];
     };
     version: string;
     platform: string[];
     tags: string[];
     weight: number;
+    // The library folder the game is installed in, null for the default library
+    install_location: string | null;
+    installed_version: string | null;
+    // Paths of the install folder kept as they are by updates and repairs
+    preserved_paths: string[];
+    // "automatic", "notify" or "manual", null to follow the launcher settings
+    update_policy: "automatic" | "notify" | "manual" | null;
+    // The revision the game is pinned to, it is not updated automatically while set
+    pinned_revision: number | null;
+    // The revision kept to roll back the last update, null if none
+    rollback_revision: number | null;
+    rollback_version: string | null;
 }
 
 export default Game;
\ No newline at end of file
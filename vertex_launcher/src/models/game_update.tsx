@@ -0,0 +1,14 @@
+// An update available for an installed game, returned by the get_updates command
+interface GameUpdate {
+    game_id: number;
+    title: string;
+    installed_version: string | null;
+    version: string;
+    installed_revision: number;
+    revision: number;
+    // Sizes in bytes, null when unknown
+    download_size: number | null;
+    installed_size: number | null;
+}
+
+export default GameUpdate;